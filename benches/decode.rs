@@ -0,0 +1,32 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+const CONTENTS: &str = "BO_ 1 Message: 8 ECU
+ SG_ sig_0 : 0|8@1+ (0.1,0) [0|25.5] \"unit\" GATEWAY
+ SG_ sig_1 : 8|8@1- (1,-40) [0|0] \"unit\" GATEWAY
+ SG_ sig_2 : 16|16@1+ (0.01,0) [0|655.35] \"unit\" GATEWAY
+ SG_ sig_3 : 39|16@0+ (1,0) [0|65535] \"unit\" GATEWAY
+ SG_ sig_4 : 48|4@1+ (1,0) [0|15] \"unit\" GATEWAY
+ SG_ sig_5 : 52|12@1- (0.5,0) [0|0] \"unit\" GATEWAY
+";
+
+fn decode_benchmark(c: &mut Criterion) {
+    let dbc = import_dbc::parse(CONTENTS).unwrap();
+    let message = &dbc.messages[0];
+    let data = [0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0xDE, 0xF0];
+
+    c.bench_function("decode to HashMap", |b| {
+        b.iter(|| message.decode(black_box(&data)))
+    });
+
+    let mut buffer = Vec::with_capacity(message.signals.len());
+    c.bench_function("decode into buffer", |b| {
+        b.iter(|| {
+            buffer.clear();
+            message.decode_into(black_box(&data), &mut buffer);
+            buffer.len()
+        })
+    });
+}
+
+criterion_group!(benches, decode_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,25 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+fn synthetic_dbc(messages: u32, signals: u32) -> String {
+    let mut contents = String::from("VERSION \"\"\n\nBU_: ECU GATEWAY\n");
+    for id in 0..messages {
+        contents.push_str(&format!("\nBO_ {} Message{}: 8 ECU\n", id, id));
+        for i in 0..signals {
+            contents.push_str(&format!(
+                " SG_ sig_{}_{} : {}|{}@1+ (0.1,0) [0|6553.5] \"unit\" GATEWAY\n",
+                id, i, (i * 64 / signals) % 64, 64 / signals));
+        }
+    }
+    contents
+}
+
+fn parse_benchmark(c: &mut Criterion) {
+    // 1000 messages of 10 signals each
+    let contents = synthetic_dbc(1000, 10);
+    c.bench_function("parse 10k signals", |b| {
+        b.iter(|| import_dbc::parse(black_box(&contents)).unwrap())
+    });
+}
+
+criterion_group!(benches, parse_benchmark);
+criterion_main!(benches);
@@ -1,349 +1,5778 @@
+// Without the `std` feature the crate is no_std and only needs alloc.
+// File access and the command line entry points live behind `std`,
+// the parser itself does not touch the file system
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+#[cfg(all(test, not(feature = "std")))]
+extern crate std;
+
+#[cfg(not(feature = "std"))]
+use alloc::{format, string::{String, ToString}, vec, vec::Vec};
+// The maps in the public API, the same type with and without std
+pub use hashbrown::HashMap;
+#[cfg(feature = "std")]
 use std::fs;
-use std::error::Error;
-use lazy_static::lazy_static;
-use regex::Regex;
-use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::path::Path;
+#[cfg(feature = "std")]
+use std::io::{self, BufRead};
+use core::fmt;
+use core::hash::Hash;
+use core::str::FromStr;
+use core::convert::TryFrom;
+use core::error::Error;
+use regex_automata::meta;
+use regex_automata::util::captures;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+#[cfg(feature = "flate2")]
+use std::io::Read;
 
+#[cfg(feature = "std")]
+#[derive(Debug, PartialEq)]
 pub struct Config {
-    pub filename: String,
+    pub filenames: Vec<String>,
+    pub format: OutputFormat,
+    pub id_range: Option<(u32, u32)>
 }
 
-#[derive(Debug)]
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Debug,
+    #[cfg(feature = "serde")]
+    Json,
+    Csv
+}
+
+#[derive(Debug, Default)]
+pub struct DbcBuilder {
+    version: Option<String>,
+    nodes: Vec<Node>,
+    messages: Vec<Message>
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Dbc {
+    pub version: Option<String>,
+    pub baudrate: Option<u32>,
     pub nodes: Vec<Node>,
-    pub messages: Vec<Message>
+    pub messages: Vec<Message>,
+    pub attribute_definitions: Vec<AttributeDefinition>,
+    pub attributes: HashMap<String, AttributeValue>,
+    pub node_attributes: HashMap<String, HashMap<String, AttributeValue>>,
+    pub env_vars: Vec<EnvVar>,
+    pub value_tables: HashMap<String, HashMap<i64, String>>,
+    pub warnings: Vec<ParseWarning>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    message_index: HashMap<u32, usize>
 }
 
 #[derive(Debug, PartialEq)]
-enum DbcError {
+pub enum DbcError {
     WrongType,
-    InvalidContent
+    InvalidContent,
+    InvalidLine { line: usize, content: String, reason: &'static str },
+    IoError(String),
+    SerializationError(String),
+    NumberParse { line: usize, field: &'static str },
+    UnknownReference(String),
+    DuplicateMessageId(u32),
+    DuplicateSignalName(String)
+}
+
+// Callbacks for parse_with_visitor, made in the order the elements appear in
+// the file. Messages are reported before their signals, so they never contain
+// any signals themselves, and later lines referring to messages or signals
+// (comments, value descriptions, attributes, ...) have callbacks of their own
+pub trait DbcVisitor {
+    fn on_version(&mut self, _version: &str) {}
+    fn on_baudrate(&mut self, _baudrate: Option<u32>) {}
+    fn on_node(&mut self, _node: &Node) {}
+    fn on_message(&mut self, _message: &Message) {}
+    fn on_signal(&mut self, _message: &Message, _signal: &Signal) {}
+    fn on_value_table(&mut self, _name: &str, _descriptions: &HashMap<i64, String>) {}
+    fn on_value_descriptions(&mut self, _message_id: u32, _signal_name: &str, _descriptions: &HashMap<i64, String>) {}
+    fn on_extended_multiplex(&mut self, _message_id: u32, _signal_name: &str, _multiplex: &ExtendedMultiplex) {}
+    fn on_value_type(&mut self, _message_id: u32, _signal_name: &str, _value_type: ValueType) {}
+    fn on_transmitters(&mut self, _message_id: u32, _transmitters: &[String]) {}
+    fn on_signal_group(&mut self, _message_id: u32, _group: &SignalGroup) {}
+    fn on_attribute_definition(&mut self, _definition: &AttributeDefinition) {}
+    fn on_attribute_default(&mut self, _name: &str, _value: &AttributeValue) {}
+    fn on_attribute(&mut self, _name: &str, _value: &AttributeValue) {}
+    fn on_node_attribute(&mut self, _node: &str, _name: &str, _value: &AttributeValue) {}
+    fn on_message_attribute(&mut self, _message_id: u32, _name: &str, _value: &AttributeValue) {}
+    fn on_signal_attribute(&mut self, _message_id: u32, _signal_name: &str, _name: &str, _value: &AttributeValue) {}
+    fn on_env_var(&mut self, _env_var: &EnvVar) {}
+    fn on_message_comment(&mut self, _message_id: u32, _text: &str) {}
+    fn on_signal_comment(&mut self, _message_id: u32, _signal_name: &str, _text: &str) {}
+    fn on_node_comment(&mut self, _node: &str, _text: &str) {}
+    fn on_warning(&mut self, _warning: &ParseWarning) {}
+}
+
+// Serializes a whole database into one of the supported output formats
+pub trait Exporter {
+    fn export(&self, dbc: &Dbc) -> String;
 }
 
+#[cfg(feature = "serde")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonExporter;
+
+// JSON with the message and signal field names of Python's cantools
+#[cfg(feature = "serde")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CantoolsExporter;
+
+// One row per signal, see Dbc::to_signal_csv
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CsvExporter;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DbcExporter;
+
 trait DbcType {
     const TAG: &'static str;
     const REGEX: &'static str;
-    fn from(cap: &regex::Captures) -> Self;
+    fn from(cap: &Captures) -> Result<Self, DbcError> where Self: Sized;
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Node {
-    pub name: String
+    pub name: String,
+    pub comment: Option<String>
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Message {
     pub id: u32,
     pub name: String,
     pub size: u8,
-    pub signals: Vec<Signal>
+    pub transmitters: Vec<String>,
+    pub signals: Vec<Signal>,
+    pub signal_groups: Vec<SignalGroup>,
+    pub comment: Option<String>,
+    pub attributes: HashMap<String, AttributeValue>,
+    pub source_line: usize
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct SignalGroup {
+    pub name: String,
+    pub repetitions: u32,
+    pub signals: Vec<String>
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Signal {
     pub name: String,
+    pub multiplexer: MultiplexIndicator,
     pub start_bit: u16,
     pub size: u16,
     pub is_little_endian: bool,
     pub is_signed: bool,
-    pub factor: String,
-    pub offset: String,
-    pub value_min: String,
-    pub value_max: String,
-    pub unit: String
+    pub value_type: ValueType,
+    pub factor: f64,
+    pub offset: f64,
+    pub value_min: f64,
+    pub value_max: f64,
+    pub unit: String,
+    pub receivers: Vec<String>,
+    pub value_descriptions: HashMap<i64, String>,
+    pub comment: Option<String>,
+    pub attributes: HashMap<String, AttributeValue>,
+    pub extended_multiplex: Option<ExtendedMultiplex>,
+    pub source_line: usize
 }
 
-impl Config {
-    pub fn new(args: &[String]) -> Result<Config, &'static str>  {
-        if args.len() < 1 {
-            return Err("not enough arguments");
-        }
+// Extended multiplexing (SG_MUL_VAL_): the signal is active while the
+// raw value of the multiplexor lies within any of the inclusive ranges
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ExtendedMultiplex {
+    pub multiplexor: String,
+    pub ranges: Vec<(u64, u64)>
+}
 
-        let filename = args[1].clone();
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ParseWarning {
+    pub line: usize,
+    pub content: String
+}
 
-        Ok(Self { filename })
-    }
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct DbcStats {
+    pub nodes: usize,
+    pub messages: usize,
+    pub signals: usize,
+    pub multiplexed_signals: usize,
+    pub min_message_size: Option<u8>,
+    pub max_message_size: Option<u8>
 }
 
-pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
-    let contents = fs::read_to_string(config.filename)?;
-    let dbc = parse(&contents);
-    println!("{:?}", dbc);
+// Names and ids used more than once, each listed once in file order
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct NameAudit {
+    pub duplicate_message_names: Vec<String>,
+    pub duplicate_signal_names: Vec<String>,
+    pub duplicate_message_ids: Vec<u32>
+}
 
-    Ok(())
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct DbcDiff {
+    pub added_messages: Vec<u32>,
+    pub removed_messages: Vec<u32>,
+    pub changed_messages: Vec<MessageDiff>
 }
 
-pub fn parse(contents: &str) -> Dbc {
-    let mut nodes: Vec<Node> = Vec::new();
-    let mut messages: Vec<Message> = Vec::new();
-    let mut signals: Vec<Signal> = Vec::new();
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct MessageDiff {
+    pub id: u32,
+    pub changes: Vec<FieldChange>,
+    pub added_signals: Vec<String>,
+    pub removed_signals: Vec<String>,
+    pub changed_signals: Vec<SignalDiff>
+}
 
-    let mut in_message = false;
-    for (i, line) in contents.lines().enumerate() {
-        if !in_message {
-            match parse_type_vec(line) {
-                Ok(new_nodes) => {
-                    nodes = new_nodes;
-                },
-                Err(DbcError::InvalidContent) => {
-                    panic!("Error when parsing line {}: {}. Invalid syntax for nodes.", i+1, line);
-                },
-                Err(_) => {},
-            }
-            
-            match parse_type(line) {
-                Ok(new_message) => {
-                    in_message = true;
-                    messages.push(new_message);
-                },
-                Err(DbcError::InvalidContent) => {
-                    panic!("Error when parsing line {}: {}. Invalid message start.", i+1, line);
-                },
-                Err(_) => {},
-            }
-        }
-        else {
-            let current_message = messages.last_mut().unwrap();
-            match parse_type(line) {
-                Ok(new_signal) => {
-                    in_message = true;
-                    signals.push(new_signal);
-                },
-                Err(DbcError::InvalidContent) => {
-                    panic!("Error when parsing line {}: {}. Invalid signal.", i+1, line);
-                },
-                Err(_) => {
-                    // In this case, the message block ended so the
-                    // signals are pushed to the current message and
-                    // the message is finished
-                    in_message = false;
-                    current_message.signals = signals.clone();
-                    signals.clear();
-                },
-            }
-        }
-    }
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct SignalDiff {
+    pub name: String,
+    pub changes: Vec<FieldChange>
+}
 
-    // If a message block is still open, add the remaining 
-    // signals and finish it
-    if in_message {
-        let current_message = messages.last_mut().unwrap();
-        current_message.signals = signals.clone();
-    }
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub old: String,
+    pub new: String
+}
 
-    Dbc{ nodes, messages }
+#[derive(Clone, Debug, PartialEq)]
+pub enum EncodeError {
+    OutOfRange { signal: String, value: f64, min: f64, max: f64 }
 }
 
-impl DbcType for Node {
-    const TAG: &'static str = "BU_";
-    const REGEX: &'static str = r"(\w+)";
+#[derive(Clone, Debug, PartialEq)]
+pub enum ValidationError {
+    SignalOutOfBounds { message: String, signal: String },
+    DuplicateMessageId { id: u32, first: String, second: String },
+    OverlappingSignals { message: String, first: String, second: String }
+}
 
-    fn from(cap: &regex::Captures) -> Self {
-        Node { 
-            name: cap[0].to_string(),
-        }
-    }
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct EnvVar {
+    pub name: String,
+    pub var_type: EnvVarType,
+    pub minimum: f64,
+    pub maximum: f64,
+    pub unit: String,
+    pub initial_value: f64,
+    pub id: u32,
+    pub access_type: EnvVarAccess,
+    pub access_nodes: Vec<String>
 }
 
-impl DbcType for Message {
-    const TAG: &'static str = "BO_ ";
-    const REGEX: &'static str = r"BO_ (\w+) (\w+) *: (\w+) (\w+).*";
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum EnvVarType {
+    Integer,
+    Float,
+    String
+}
 
-    fn from(cap: &regex::Captures) -> Self {
-        Message { 
-            id: cap[1].parse::<u32>().unwrap(),
-            name: cap[2].to_string(),
-            size: cap[3].parse::<u8>().unwrap(),
-            signals: Vec::new()
-        }
-    }
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum EnvVarAccess {
+    Unrestricted,
+    Read,
+    Write,
+    ReadWrite
 }
 
-impl DbcType for Signal {
-    const TAG: &'static str = "SG_ ";
-    const REGEX: &'static str = r#"SG_ (\w+) : (\d+)\|(\d+)@(\d+)([\+|\-]) \(([0-9.+\-eE]+),([0-9.+\-eE]+)\) \[([0-9.+\-eE]+)\|([0-9.+\-eE]+)\] "(.*)" (.*)"#;
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum MultiplexIndicator {
+    None,
+    Multiplexor,
+    MultiplexedBy(u16)
+}
 
-    fn from(cap: &regex::Captures) -> Self {
-        Signal { 
-            name: cap[1].to_string(),
-            start_bit: cap[2].parse().unwrap(),
-            size: cap[3].parse().unwrap(),
-            is_little_endian: cap[4].to_string() == "1",
-            is_signed: cap[5].to_string() == "-",
-            factor: cap[6].to_string(),
-            offset: cap[7].to_string(),
-            value_min: cap[8].to_string(),
-            value_max: cap[9].to_string(),
-            unit: cap[10].to_string()
-        }
-    }
-}
-
-lazy_static! {
-    static ref HASHMAP: HashMap<&'static str, Regex> = {
-        let mut m = HashMap::new();
-        m.insert(Node::REGEX, Regex::new(Node::REGEX).unwrap());
-        m.insert(Message::REGEX, Regex::new(Message::REGEX).unwrap());
-        m.insert(Signal::REGEX, Regex::new(Signal::REGEX).unwrap());
-        m
-    };
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum ByteOrder {
+    LittleEndian,
+    BigEndian
 }
 
-fn parse_type<T: DbcType>(content: &str) -> Result<T, DbcError> {
-    let content = content.trim();
-    let re = HASHMAP.get(T::REGEX).unwrap();
+// Float and Double signals (SIG_VALTYPE_ 1 and 2) hold the IEEE 754
+// bits of the raw value instead of an integer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum ValueType {
+    Integer,
+    Float,
+    Double
+}
 
-    if !re.is_match(content) {
-        if !content.starts_with(T::TAG) {
-            return Err(DbcError::WrongType);
-        }
-        else {
-            return Err(DbcError::InvalidContent);
-        }
-    }
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct AttributeDefinition {
+    pub name: String,
+    pub object: AttributeObject,
+    pub value_type: AttributeType,
+    pub default: Option<AttributeValue>
+}
 
-    let cap = re.captures(content).unwrap();
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum AttributeObject {
+    Global,
+    Node,
+    Message,
+    Signal,
+    EnvVar
+}
 
-    Ok (
-        T::from(&cap)
-    )
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum AttributeType {
+    Int { min: i64, max: i64 },
+    Hex { min: i64, max: i64 },
+    Float { min: f64, max: f64 },
+    String,
+    Enum(Vec<String>)
 }
 
-fn parse_type_vec<T: DbcType>(content: &str) -> Result<Vec<T>, DbcError> {
-    let content = content.trim();
-    let re = HASHMAP.get(T::REGEX).unwrap();
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum AttributeValue {
+    Int(i64),
+    Float(f64),
+    String(String)
+}
+
+#[derive(Debug)]
+enum AttributeTarget {
+    Global,
+    Node(String),
+    Message(u32),
+    Signal(u32, String),
+    EnvVar
+}
+
+#[derive(Debug)]
+struct AttributeDefault {
+    name: String,
+    value: String
+}
+
+#[derive(Debug)]
+struct AttributeAssignment {
+    name: String,
+    target: AttributeTarget,
+    value: String
+}
+
+#[derive(Debug)]
+enum Comment {
+    Message { id: u32, text: String },
+    Signal { message_id: u32, signal_name: String, text: String },
+    Node { name: String, text: String },
+    Other
+}
+
+struct LogicalLines<I> {
+    lines: core::iter::Enumerate<I>
+}
+
+#[derive(Debug)]
+struct Version(String);
+
+// BS_: [baudrate : BTR1 , BTR2], only the baudrate is kept
+#[derive(Debug)]
+struct BitTiming(Option<u32>);
+
+#[derive(Debug)]
+struct MessageTransmitters {
+    message_id: u32,
+    transmitters: Vec<String>
+}
+
+#[derive(Debug)]
+struct MessageSignalGroup {
+    message_id: u32,
+    group: SignalGroup
+}
+
+#[derive(Debug)]
+struct SignalValueType {
+    message_id: u32,
+    signal_name: String,
+    value_type: ValueType
+}
+
+#[derive(Debug)]
+struct SignalMultiplexValues {
+    message_id: u32,
+    signal_name: String,
+    multiplex: ExtendedMultiplex
+}
+
+// The descriptions are either listed or taken from a value table
+#[derive(Debug)]
+struct ValueDescriptions {
+    message_id: u32,
+    signal_name: String,
+    table: Option<String>,
+    descriptions: HashMap<i64, String>
+}
+
+#[derive(Debug)]
+struct ValueTable {
+    name: String,
+    descriptions: HashMap<i64, String>
+}
+
+// Builds the Dbc returned by parse_reader out of the visitor callbacks
+#[derive(Default)]
+struct DbcCollector {
+    version: Option<String>,
+    baudrate: Option<u32>,
+    nodes: Vec<Node>,
+    messages: Vec<Message>,
+    warnings: Vec<ParseWarning>,
+    attribute_definitions: Vec<AttributeDefinition>,
+    attributes: HashMap<String, AttributeValue>,
+    node_attributes: HashMap<String, HashMap<String, AttributeValue>>,
+    env_vars: Vec<EnvVar>,
+    value_tables: HashMap<String, HashMap<i64, String>>
+}
+
+#[cfg(feature = "std")]
+impl Config {
+    pub fn new(args: &[String]) -> Result<Config, &'static str>  {
+        let mut filenames = Vec::new();
+        let mut format = OutputFormat::Debug;
+        let mut id_range = None;
+
+        let mut args = args.iter().skip(1);
+        while let Some(arg) = args.next() {
+            if arg == "--format" {
+                format = Config::parse_format(args.next().ok_or("missing output format")?)?;
+            }
+            else if let Some(value) = arg.strip_prefix("--format=") {
+                format = Config::parse_format(value)?;
+            }
+            else if arg == "--id-range" {
+                id_range = Some(Config::parse_id_range(args.next().ok_or("missing id range")?)?);
+            }
+            else if let Some(value) = arg.strip_prefix("--id-range=") {
+                id_range = Some(Config::parse_id_range(value)?);
+            }
+            else {
+                filenames.push(arg.clone());
+            }
+        }
+
+        if filenames.is_empty() {
+            return Err("not enough arguments");
+        }
 
-    if !content.starts_with(T::TAG) {
-        return Err(DbcError::WrongType);
+        Ok(Self { filenames, format, id_range })
     }
-    
-    let mut objs: Vec<T> = Vec::new();
 
-    for cap in re.captures_iter(content) {
-        let name = cap[0].to_string();
-        if name != T::TAG {
-            let node = T::from(&cap);
-            objs.push(node);
+    fn parse_format(value: &str) -> Result<OutputFormat, &'static str> {
+        match value {
+            "debug" => Ok(OutputFormat::Debug),
+            #[cfg(feature = "serde")]
+            "json" => Ok(OutputFormat::Json),
+            #[cfg(not(feature = "serde"))]
+            "json" => Err("json output requires the serde feature"),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err("unknown output format"),
         }
     }
 
-    Ok(objs)
+    // An inclusive range of CAN ids such as 0x100-0x1FF or 256-511
+    fn parse_id_range(value: &str) -> Result<(u32, u32), &'static str> {
+        let parse_id = |id: &str| match id.strip_prefix("0x").or_else(|| id.strip_prefix("0X")) {
+            Some(hex) => u32::from_str_radix(hex, 16),
+            None => id.parse(),
+        };
+
+        let (low, high) = value.split_once('-').ok_or("invalid id range")?;
+        match (parse_id(low.trim()), parse_id(high.trim())) {
+            (Ok(low), Ok(high)) if low <= high => Ok((low, high)),
+            _ => Err("invalid id range"),
+        }
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+impl DbcBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-    fn parse_signal(contents: &str) -> Result<Signal, DbcError> {
-        parse_type::<Signal>(contents)
+    pub fn version(mut self, version: &str) -> Self {
+        self.version = Some(version.to_string());
+        self
     }
 
-    fn parse_message(contents: &str) -> Result<Message, DbcError> {
-        parse_type::<Message>(contents)
+    pub fn add_node(mut self, name: &str) -> Self {
+        self.nodes.push(Node { name: name.to_string(), comment: None });
+        self
     }
 
-    fn parse_nodes(contents: &str) -> Result<Vec<Node>, DbcError> {
-        parse_type_vec::<Node>(contents)
+    pub fn add_message(mut self, message: Message) -> Self {
+        self.messages.push(message);
+        self
     }
 
-    struct Setup<'a>{
-        test_messages: &'a str
+    pub fn add_signal_to(mut self, message_id: u32, signal: Signal) -> Result<Self, DbcError> {
+        match self.messages.iter_mut().find(|message| message.id == message_id) {
+            Some(message) => message.signals.push(signal),
+            None => return Err(DbcError::UnknownReference(format!("message {}", message_id))),
+        }
+
+        Ok(self)
     }
 
-    impl Setup<'_> {
-        fn new() -> Self {
-            let test_messages = "
-BU_: TCU VEHICLE
+    pub fn build(self) -> Dbc {
+        let mut dbc = Dbc::new(self.nodes, self.messages);
+        dbc.version = self.version;
+        dbc
+    }
+}
 
-BO_ 2566117891 MsgDummy1: 8 Vector__XXX
- SG_ dummy1sg1 : 34|2@1+ (1,0) [0|3] \"kkk\" Vector__XXX
- SG_ dummy1sg2 : 18|16@1- (1,0) [0|65535] \"\" Vector__XXX
- SG_ dummy1sg3 : 2|16@1+ (1,0) [0|65535] \"\" Vector__XXX
- SG_ dummy1sg4 : 0|2@1+ (1,0) [0|3] \"\" Vector__XXX
+impl Dbc {
+    fn new(nodes: Vec<Node>, messages: Vec<Message>) -> Self {
+        let message_index = index_messages(&messages);
 
-BO_ 2565921559 MsgDummy2: 8 Vector__XXX
- SG_ gps_longitude : 39|32@0- (1E-007,0) [-214.7483648|214.7483647] \"deg\" Vector__XXX
- SG_ gps_latitude : 7|32@0- (1E-007,0) [-214.7483648|214.7483647] \"deg\" Vector__XXX
+        Dbc {
+            version: None,
+            baudrate: None,
+            nodes,
+            messages,
+            attribute_definitions: Vec::new(),
+            attributes: HashMap::new(),
+            node_attributes: HashMap::new(),
+            env_vars: Vec::new(),
+            value_tables: HashMap::new(),
+            warnings: Vec::new(),
+            message_index
+        }
+    }
 
-BO_ 2565986819 MsgDummy3: 8 TCU
- SG_ dummy3sg1 : 16|16@1+ (0.125,0) [0|8191.875] \"\" Vector__XXX
-";
-            Self { test_messages }
+    pub fn message_by_id(&self, id: u32) -> Option<&Message> {
+        match self.message_index.get(&id).and_then(|&i| self.messages.get(i)) {
+            Some(message) if message.id == id => Some(message),
+            // The index no longer matches if the messages were
+            // edited after parsing, so fall back to a linear scan
+            _ => self.messages.iter().find(|message| message.id == id),
         }
     }
 
-    #[test]
-    fn valid_message_start() {
-        let content = "BO_ 2566117891 MsgDummy1: 8 Vector__XXX";
-        assert_eq!(parse_message(content).is_ok(), true);
+    pub fn message_by_name(&self, name: &str) -> Option<&Message> {
+        self.messages.iter().find(|message| message.name == name)
     }
 
-    #[test]
-    fn invalid_message_start() {
-        let content = "BO_ 2566117891 MsgDummy1: Vector__XXX";
-        assert_eq!(parse_message(content).err().unwrap(), DbcError::InvalidContent);
+    pub fn node_names(&self) -> Vec<&str> {
+        self.nodes.iter().map(|node| node.name.as_str()).collect()
     }
 
-    #[test]
-    fn not_message() {
-        let content = "SG_ dummy1sg1 : 34|2@1+ (1,0) [0|3] \"kkk\" Vector__XXX";
-        assert_eq!(parse_message(content).err().unwrap(), DbcError::WrongType);
+    pub fn messages_sent_by(&self, node: &str) -> Vec<&Message> {
+        self.messages.iter()
+            .filter(|message| message.transmitters.iter().any(|transmitter| transmitter == node))
+            .collect()
     }
 
-    #[test]
-    fn valid_signal() {
-        let content = "SG_ dummy1sg1 : 34|2@1+ (1,0) [0|3] \"kkk\" Vector__XXX";
-        assert_eq!(parse_signal(content).is_ok(), true);
+    pub fn signals_received_by(&self, node: &str) -> Vec<&Signal> {
+        self.iter_signals()
+            .map(|(_, signal)| signal)
+            .filter(|signal| signal.receivers.iter().any(|receiver| receiver == node))
+            .collect()
     }
 
-    #[test]
-    fn invalid_signal() {
-        let content = "SG_ dummy1sg1 : 34|21+ (1,0) [0|3] \"kkk\" Vector__XXX";
-        assert_eq!(parse_signal(content).err().unwrap(), DbcError::InvalidContent);
+    pub fn iter_signals(&self) -> impl Iterator<Item = (&Message, &Signal)> {
+        self.messages.iter()
+            .flat_map(|message| message.signals.iter().map(move |signal| (message, signal)))
     }
 
-    #[test]
-    fn not_signal() {
-        let content = "BO_ 2566117891 MsgDummy1: 8 Vector__XXX";
-        assert_eq!(parse_signal(content).err().unwrap(), DbcError::WrongType);
+    // Ordered by the CAN identifier without the extended frame flag,
+    // so extended and standard messages are interleaved
+    pub fn sort_messages_by_id(&mut self) {
+        self.messages.sort_by_key(|message| message.can_id());
+        self.reindex();
     }
 
-    #[test]
-    fn num_signals() {
-        let setup = Setup::new();
-        let messages = parse(setup.test_messages).messages;
-        assert_eq!(messages[0].signals.len(), 4);
-        assert_eq!(messages[1].signals.len(), 2);
-        assert_eq!(messages[2].signals.len(), 1);
+    pub fn remove_message(&mut self, id: u32) -> Option<Message> {
+        let position = self.messages.iter().position(|message| message.id == id)?;
+        let message = self.messages.remove(position);
+        self.reindex();
+        Some(message)
     }
 
-    #[test]
-    fn signal_values() {
-        let setup = Setup::new();
-        let messages = parse(setup.test_messages).messages;
-        assert_eq!(messages[1].signals[0].name, "gps_longitude");
-        assert_eq!(messages[1].signals[0].start_bit, 39);
-        assert_eq!(messages[1].signals[0].size, 32);
-        assert_eq!(messages[1].signals[0].value_min, "-214.7483648");
-        assert_eq!(messages[1].signals[0].value_max, "214.7483647");
-        assert_eq!(messages[1].signals[0].unit, "deg");
-        assert_eq!(messages[1].signals[0].is_little_endian, false);
-        assert_eq!(messages[1].signals[0].is_signed, true);
+    // The methods of Dbc keep the index up to date, only edits made
+    // directly to `messages` need a call to this
+    pub fn reindex(&mut self) {
+        self.message_index = index_messages(&self.messages);
     }
 
-    #[test]
-    fn nodes() {
-        let content = "BU_: TCU VEHICLE";
-        let nodes = parse_nodes(content).unwrap();
-        assert_eq!(nodes[0].name, "TCU");
-        assert_eq!(nodes[1].name, "VEHICLE");
+    // See Message::in_id_range
+    pub fn messages_in_range(&self, low: u32, high: u32) -> Vec<&Message> {
+        self.messages.iter()
+            .filter(|message| message.in_id_range(low, high))
+            .collect()
     }
 
-    #[test]
-    fn all_nodes() {
-        let setup = Setup::new();
-        let nodes = parse(setup.test_messages).nodes;
-        assert_eq!(nodes[0].name, "TCU");
-        assert_eq!(nodes[1].name, "VEHICLE");
+    pub fn retain_id_range(&mut self, low: u32, high: u32) {
+        self.messages.retain(|message| message.in_id_range(low, high));
+        self.reindex();
+    }
+
+    pub fn messages_sorted(&self) -> Vec<&Message> {
+        let mut messages: Vec<&Message> = self.messages.iter().collect();
+        messages.sort_by_key(|message| message.can_id());
+        messages
+    }
+
+    pub fn statistics(&self) -> DbcStats {
+        DbcStats {
+            nodes: self.nodes.len(),
+            messages: self.messages.len(),
+            signals: self.iter_signals().count(),
+            multiplexed_signals: self.iter_signals()
+                .filter(|(_, signal)| matches!(signal.multiplexer, MultiplexIndicator::MultiplexedBy(_)))
+                .count(),
+            min_message_size: self.messages.iter().map(|message| message.size).min(),
+            max_message_size: self.messages.iter().map(|message| message.size).max()
+        }
+    }
+
+    // Messages are matched by id and signals by name
+    pub fn diff(&self, other: &Dbc) -> DbcDiff {
+        let mut diff = DbcDiff::default();
+
+        for message in &self.messages {
+            match other.message_by_id(message.id) {
+                Some(other_message) => {
+                    let message_diff = message.diff(other_message);
+                    if !message_diff.is_empty() {
+                        diff.changed_messages.push(message_diff);
+                    }
+                },
+                None => diff.removed_messages.push(message.id),
+            }
+        }
+        for message in &other.messages {
+            if self.message_by_id(message.id).is_none() {
+                diff.added_messages.push(message.id);
+            }
+        }
+
+        diff
+    }
+
+    pub fn merge(&mut self, other: Dbc) -> Result<(), DbcError> {
+        // Nothing is merged if any message id is already taken
+        if let Some(message) = other.messages.iter().find(|message| self.message_by_id(message.id).is_some()) {
+            return Err(DbcError::DuplicateMessageId(message.id));
+        }
+
+        if self.version.is_none() {
+            self.version = other.version;
+        }
+        if self.baudrate.is_none() {
+            self.baudrate = other.baudrate;
+        }
+
+        for node in other.nodes {
+            if !self.nodes.iter().any(|existing| existing.name.eq_ignore_ascii_case(&node.name)) {
+                self.nodes.push(node);
+            }
+        }
+
+        for message in other.messages {
+            self.message_index.insert(message.id, self.messages.len());
+            self.messages.push(message);
+        }
+
+        for definition in other.attribute_definitions {
+            if self.attribute_definition(&definition.name).is_none() {
+                self.attribute_definitions.push(definition);
+            }
+        }
+
+        for (name, value) in other.attributes {
+            self.attributes.entry(name).or_insert(value);
+        }
+
+        for (node, attributes) in other.node_attributes {
+            let existing = self.node_attributes.entry(node).or_default();
+            for (name, value) in attributes {
+                existing.entry(name).or_insert(value);
+            }
+        }
+
+        self.env_vars.extend(other.env_vars);
+
+        for (name, table) in other.value_tables {
+            self.value_tables.entry(name).or_insert(table);
+        }
+
+        // The warnings of the other file are dropped, their line
+        // numbers would not match anything in this one
+
+        Ok(())
+    }
+
+    pub fn find_signal(&self, name: &str) -> Vec<(&Message, &Signal)> {
+        self.iter_signals().filter(|(_, signal)| signal.name == name).collect()
+    }
+
+    pub fn find_unique_signal(&self, name: &str) -> Result<(&Message, &Signal), DbcError> {
+        match self.find_signal(name).as_slice() {
+            [] => Err(DbcError::UnknownReference(format!("signal {}", name))),
+            [found] => Ok(*found),
+            _ => Err(DbcError::DuplicateSignalName(name.to_string())),
+        }
+    }
+
+    pub fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+
+        for (i, message) in self.messages.iter().enumerate() {
+            if let Some(other) = self.messages[..i].iter().find(|other| other.id == message.id) {
+                errors.push(ValidationError::DuplicateMessageId {
+                    id: message.id,
+                    first: other.name.clone(),
+                    second: message.name.clone()
+                });
+            }
+
+            let frame_bits = u16::from(message.size) * 8;
+            for signal in &message.signals {
+                // Positions past the largest frame are left out of bit_positions
+                let positions = signal.bit_positions();
+                if positions.len() < usize::from(signal.size) || positions.iter().any(|&bit| bit >= frame_bits) {
+                    errors.push(ValidationError::SignalOutOfBounds {
+                        message: message.name.clone(),
+                        signal: signal.name.clone()
+                    });
+                }
+            }
+
+            for (first, second) in message.overlapping_signals() {
+                errors.push(ValidationError::OverlappingSignals {
+                    message: message.name.clone(),
+                    first: first.name.clone(),
+                    second: second.name.clone()
+                });
+            }
+        }
+
+        errors
+    }
+
+    // Signal names are checked across all messages, as generated
+    // code usually puts every signal in one namespace
+    pub fn audit_names(&self) -> NameAudit {
+        NameAudit {
+            duplicate_message_names: duplicates(self.messages.iter().map(|message| &message.name))
+                .into_iter().cloned().collect(),
+            duplicate_signal_names: duplicates(self.iter_signals().map(|(_, signal)| &signal.name))
+                .into_iter().cloned().collect(),
+            duplicate_message_ids: duplicates(self.messages.iter().map(|message| message.id))
+        }
+    }
+
+    pub fn to_dbc_string(&self) -> String {
+        let mut output = String::new();
+        output.push_str(&format!("VERSION \"{}\"\n\n", self.version.as_deref().unwrap_or("")));
+        output.push_str("NS_ :\n\n");
+        match self.baudrate {
+            Some(baudrate) => output.push_str(&format!("BS_: {}\n\n", baudrate)),
+            None => output.push_str("BS_:\n\n"),
+        }
+
+        output.push_str("BU_:");
+        for node in &self.nodes {
+            output.push(' ');
+            output.push_str(&node.name);
+        }
+        output.push_str("\n\n");
+
+        let mut tables: Vec<_> = self.value_tables.iter().collect();
+        tables.sort_by_key(|(name, _)| name.as_str());
+        for (name, table) in &tables {
+            output.push_str(&format!("VAL_TABLE_ {}", name));
+            push_value_descriptions(&mut output, table);
+        }
+        if !tables.is_empty() {
+            output.push('\n');
+        }
+
+        for message in &self.messages {
+            output.push_str(&format!("{}\n", message));
+        }
+
+        for message in self.messages.iter().filter(|message| message.transmitters.len() > 1) {
+            output.push_str(&format!("BO_TX_BU_ {} : {};\n", message.id, message.transmitters.join(",")));
+        }
+
+        for env_var in &self.env_vars {
+            output.push_str(&format!("{}\n", env_var));
+        }
+
+        for node in &self.nodes {
+            if let Some(comment) = &node.comment {
+                output.push_str(&format!("CM_ BU_ {} \"{}\";\n", node.name, escape(comment)));
+            }
+        }
+        for message in &self.messages {
+            if let Some(comment) = &message.comment {
+                output.push_str(&format!("CM_ BO_ {} \"{}\";\n", message.id, escape(comment)));
+            }
+            for signal in &message.signals {
+                if let Some(comment) = &signal.comment {
+                    output.push_str(&format!("CM_ SG_ {} {} \"{}\";\n", message.id, signal.name, escape(comment)));
+                }
+            }
+        }
+
+        for definition in &self.attribute_definitions {
+            output.push_str(&format!("{}\n", definition));
+        }
+        for definition in &self.attribute_definitions {
+            if let Some(default) = &definition.default {
+                output.push_str(&format!("BA_DEF_DEF_ \"{}\" {};\n", definition.name, default));
+            }
+        }
+
+        // Values equal to the default are implied by BA_DEF_DEF_
+        let explicit_attributes = |attributes| {
+            let mut attributes = sorted_attributes(attributes);
+            attributes.retain(|(name, value)| {
+                self.attribute_definition(name).and_then(|definition| definition.default.as_ref()) != Some(*value)
+            });
+            attributes
+        };
+
+        for (name, value) in explicit_attributes(&self.attributes) {
+            output.push_str(&format!("BA_ \"{}\" {};\n", name, value));
+        }
+        for node in &self.nodes {
+            if let Some(attributes) = self.node_attributes.get(&node.name) {
+                for (name, value) in explicit_attributes(attributes) {
+                    output.push_str(&format!("BA_ \"{}\" BU_ {} {};\n", name, node.name, value));
+                }
+            }
+        }
+        for message in &self.messages {
+            for (name, value) in explicit_attributes(&message.attributes) {
+                output.push_str(&format!("BA_ \"{}\" BO_ {} {};\n", name, message.id, value));
+            }
+            for signal in &message.signals {
+                for (name, value) in explicit_attributes(&signal.attributes) {
+                    output.push_str(&format!("BA_ \"{}\" SG_ {} {} {};\n", name, message.id, signal.name, value));
+                }
+            }
+        }
+
+        for message in &self.messages {
+            for signal in message.signals.iter().filter(|signal| !signal.value_descriptions.is_empty()) {
+                output.push_str(&format!("VAL_ {} {}", message.id, signal.name));
+                push_value_descriptions(&mut output, &signal.value_descriptions);
+            }
+        }
+
+        for message in &self.messages {
+            for signal in &message.signals {
+                if let Some(multiplex) = &signal.extended_multiplex {
+                    let ranges: Vec<String> = multiplex.ranges.iter()
+                        .map(|(min, max)| format!("{}-{}", min, max))
+                        .collect();
+                    output.push_str(&format!("SG_MUL_VAL_ {} {} {} {};\n",
+                        message.id, signal.name, multiplex.multiplexor, ranges.join(", ")));
+                }
+            }
+        }
+
+        for message in &self.messages {
+            for group in &message.signal_groups {
+                output.push_str(&format!("SIG_GROUP_ {} {} {} :", message.id, group.name, group.repetitions));
+                for signal in &group.signals {
+                    output.push(' ');
+                    output.push_str(signal);
+                }
+                output.push_str(";\n");
+            }
+        }
+
+        for message in &self.messages {
+            for signal in &message.signals {
+                let code = match signal.value_type {
+                    ValueType::Integer => continue,
+                    ValueType::Float => 1,
+                    ValueType::Double => 2,
+                };
+                output.push_str(&format!("SIG_VALTYPE_ {} {} : {};\n", message.id, signal.name, code));
+            }
+        }
+
+        output
+    }
+
+    pub fn to_signal_csv(&self) -> String {
+        let mut output = String::from(
+            "message_id,message_name,signal_name,start_bit,size,byte_order,signed,factor,offset,min,max,unit\n");
+
+        for (message, signal) in self.iter_signals() {
+            output.push_str(&format!("{},{},{},{},{},{},{},{},{},{},{},{}\n",
+                message.id, csv_field(&message.name), csv_field(&signal.name), signal.start_bit, signal.size,
+                match signal.byte_order() {
+                    ByteOrder::LittleEndian => "Intel",
+                    ByteOrder::BigEndian => "Motorola"
+                },
+                signal.is_signed,
+                signal.factor, signal.offset, signal.value_min, signal.value_max, csv_field(&signal.unit)));
+        }
+
+        output
+    }
+
+    pub fn attribute_definition(&self, name: &str) -> Option<&AttributeDefinition> {
+        self.attribute_definitions.iter().find(|definition| definition.name == name)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, Box<dyn Error>> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+fn duplicates<T: Copy + Eq + Hash>(items: impl Iterator<Item = T>) -> Vec<T> {
+    let mut counts = HashMap::new();
+    let mut duplicates = Vec::new();
+    for item in items {
+        let count = counts.entry(item).or_insert(0);
+        *count += 1;
+        if *count == 2 {
+            duplicates.push(item);
+        }
+    }
+
+    duplicates
+}
+
+fn push_value_descriptions(output: &mut String, descriptions: &HashMap<i64, String>) {
+    let mut values: Vec<_> = descriptions.iter().collect();
+    values.sort();
+    for (value, description) in values {
+        output.push_str(&format!(" {} \"{}\"", value, escape(description)));
+    }
+    output.push_str(" ;\n");
+}
+
+fn field_change<T: PartialEq + fmt::Display + ?Sized>(changes: &mut Vec<FieldChange>, field: &'static str, old: &T, new: &T) {
+    if old != new {
+        changes.push(FieldChange { field, old: old.to_string(), new: new.to_string() });
+    }
+}
+
+fn index_messages(messages: &[Message]) -> HashMap<u32, usize> {
+    let mut message_index = HashMap::new();
+    for (i, message) in messages.iter().enumerate() {
+        message_index.entry(message.id).or_insert(i);
+    }
+
+    message_index
+}
+
+fn sorted_attributes(attributes: &HashMap<String, AttributeValue>) -> Vec<(&String, &AttributeValue)> {
+    let mut attributes: Vec<_> = attributes.iter().collect();
+    attributes.sort_by(|a, b| a.0.cmp(b.0));
+    attributes
+}
+
+const EXTENDED_ID_FLAG: u32 = 0x8000_0000;
+
+// Bits in the largest (64 byte CAN-FD) frame
+const MAX_FRAME_BITS: u32 = 512;
+
+// Payload sizes of the CAN-FD length codes 9 to 15
+const CAN_FD_SIZES: [u8; 7] = [12, 16, 20, 24, 32, 48, 64];
+
+impl Message {
+    pub fn new(id: u32, name: &str, size: u8) -> Self {
+        Message {
+            id,
+            name: name.to_string(),
+            size,
+            transmitters: Vec::new(),
+            signals: Vec::new(),
+            signal_groups: Vec::new(),
+            comment: None,
+            attributes: HashMap::new(),
+            source_line: 0
+        }
+    }
+
+    pub fn is_extended(&self) -> bool {
+        self.id & EXTENDED_ID_FLAG != 0
+    }
+
+    // Sizes between two CAN-FD lengths take the next larger code
+    pub fn dlc(&self) -> u8 {
+        if self.size <= 8 {
+            return self.size;
+        }
+        match CAN_FD_SIZES.iter().position(|&size| size >= self.size) {
+            Some(i) => 9 + i as u8,
+            None => 15,
+        }
+    }
+
+    pub fn from_dlc(dlc: u8) -> Option<u8> {
+        match dlc {
+            0..=8 => Some(dlc),
+            9..=15 => Some(CAN_FD_SIZES[usize::from(dlc - 9)]),
+            _ => None,
+        }
+    }
+
+    // Both ends are included and compared with the CAN id, so
+    // the extended frame flag does not matter
+    pub fn in_id_range(&self, low: u32, high: u32) -> bool {
+        (low..=high).contains(&self.can_id())
+    }
+
+    pub fn can_id(&self) -> u32 {
+        if self.is_extended() {
+            self.id & 0x1FFF_FFFF
+        }
+        else {
+            self.id & 0x7FF
+        }
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&AttributeValue> {
+        self.attributes.get(name)
+    }
+
+    // Defaults from BA_DEF_DEF_ are already applied when parsing
+    pub fn cycle_time(&self) -> Option<u32> {
+        match self.attribute("GenMsgCycleTime") {
+            Some(AttributeValue::Int(value)) => u32::try_from(*value).ok(),
+            Some(AttributeValue::Float(value)) if *value >= 0.0 => Some(round(*value) as u32),
+            _ => None,
+        }
+    }
+
+    pub fn signal_by_name(&self, name: &str) -> Option<&Signal> {
+        self.signals.iter().find(|signal| signal.name == name)
+    }
+
+    pub fn signal_by_name_mut(&mut self, name: &str) -> Option<&mut Signal> {
+        self.signals.iter_mut().find(|signal| signal.name == name)
+    }
+
+    pub fn remove_signal(&mut self, name: &str) -> Option<Signal> {
+        let position = self.signals.iter().position(|signal| signal.name == name)?;
+        Some(self.signals.remove(position))
+    }
+
+    // One row per byte of the message, bit 0 being the least significant bit
+    // of the byte. When signals overlap, the first one declared is shown
+    pub fn bit_layout(&self) -> Vec<[Option<&str>; 8]> {
+        let mut layout = vec![[None; 8]; usize::from(self.size)];
+
+        for signal in &self.signals {
+            for position in signal.bit_positions() {
+                let position = usize::from(position);
+                if let Some(slot) = layout.get_mut(position / 8).map(|byte| &mut byte[position % 8]) {
+                    if slot.is_none() {
+                        *slot = Some(signal.name.as_str());
+                    }
+                }
+            }
+        }
+
+        layout
+    }
+
+    // Bits are numbered as in bit_layout, the first signal declared wins
+    pub fn contains_bit(&self, bit: u16) -> Option<&Signal> {
+        self.signals.iter().find(|signal| signal.bit_positions().contains(&bit))
+    }
+
+    // Bits shared by several signals (e.g. multiplexed ones) count once
+    pub fn used_bits(&self) -> u32 {
+        self.bit_layout().iter()
+            .flat_map(|byte| byte.iter())
+            .filter(|bit| bit.is_some())
+            .count() as u32
+    }
+
+    pub fn packing_density(&self) -> f32 {
+        if self.size == 0 {
+            return 0.0;
+        }
+        self.used_bits() as f32 / (f32::from(self.size) * 8.0)
+    }
+
+    pub fn overlapping_signals(&self) -> Vec<(&Signal, &Signal)> {
+        let mut pairs = Vec::new();
+        for (i, first) in self.signals.iter().enumerate() {
+            for second in &self.signals[i+1..] {
+                if first.overlaps(second) {
+                    pairs.push((first, second));
+                }
+            }
+        }
+
+        pairs
+    }
+
+    fn diff(&self, other: &Message) -> MessageDiff {
+        let mut diff = MessageDiff { id: self.id, ..MessageDiff::default() };
+        field_change(&mut diff.changes, "name", &self.name, &other.name);
+        field_change(&mut diff.changes, "size", &self.size, &other.size);
+        field_change(&mut diff.changes, "transmitters", &self.transmitters.join(","), &other.transmitters.join(","));
+
+        for signal in &self.signals {
+            match other.signal_by_name(&signal.name) {
+                Some(other_signal) => {
+                    let signal_diff = signal.diff(other_signal);
+                    if !signal_diff.changes.is_empty() {
+                        diff.changed_signals.push(signal_diff);
+                    }
+                },
+                None => diff.removed_signals.push(signal.name.clone()),
+            }
+        }
+        for signal in &other.signals {
+            if self.signal_by_name(&signal.name).is_none() {
+                diff.added_signals.push(signal.name.clone());
+            }
+        }
+
+        diff
+    }
+
+    // Plain signals and multiplexors are always active, multiplexed
+    // signals only for the multiplexor value found in the frame
+    pub fn active_signals(&self, data: &[u8]) -> Vec<&Signal> {
+        let multiplexor_value = self.multiplexor_value(data);
+        self.signals.iter()
+            .filter(|signal| self.is_active(signal, multiplexor_value, data))
+            .collect()
+    }
+
+    fn multiplexor_value(&self, data: &[u8]) -> Option<u64> {
+        self.signals.iter()
+            .find(|signal| signal.multiplexer == MultiplexIndicator::Multiplexor)
+            .map(|signal| signal.raw_value(data))
+    }
+
+    fn is_active(&self, signal: &Signal, multiplexor_value: Option<u64>, data: &[u8]) -> bool {
+        match (&signal.extended_multiplex, signal.multiplexer) {
+            (Some(multiplex), _) => self.signal_by_name(&multiplex.multiplexor)
+                .map(|multiplexor| multiplexor.raw_value(data))
+                .is_some_and(|value| multiplex.ranges.iter().any(|&(min, max)| (min..=max).contains(&value))),
+            (None, MultiplexIndicator::MultiplexedBy(value)) => multiplexor_value == Some(u64::from(value)),
+            _ => true
+        }
+    }
+
+    pub fn decode(&self, data: &[u8]) -> HashMap<String, f64> {
+        // Bytes past the declared message size are not part of the frame
+        let data = &data[..data.len().min(usize::from(self.size))];
+
+        self.active_signals(data).into_iter()
+            .map(|signal| (signal.name.clone(), signal.decode(data)))
+            .collect()
+    }
+
+    // Same values as decode, appended as (index in signals, value) pairs
+    // so a buffer can be reused without allocating for every frame
+    pub fn decode_into(&self, data: &[u8], out: &mut Vec<(usize, f64)>) {
+        let data = &data[..data.len().min(usize::from(self.size))];
+
+        let multiplexor_value = self.multiplexor_value(data);
+        for (i, signal) in self.signals.iter().enumerate() {
+            if self.is_active(signal, multiplexor_value, data) {
+                out.push((i, signal.decode(data)));
+            }
+        }
+    }
+
+    pub fn encode(&self, values: &HashMap<String, f64>) -> Vec<u8> {
+        let mut data = vec![0u8; usize::from(self.size)];
+        for signal in &self.signals {
+            if let Some(&value) = values.get(&signal.name) {
+                signal.encode(value, &mut data);
+            }
+            else if let Some(raw) = signal.start_value() {
+                // Signals left out keep the start value the ECU would send,
+                // stored as the signal's value type
+                signal.encode(signal.raw_to_physical(raw), &mut data);
+            }
+        }
+
+        data
+    }
+}
+
+impl Signal {
+    pub fn new(name: &str, start_bit: u16, size: u16) -> Self {
+        Signal {
+            name: name.to_string(),
+            multiplexer: MultiplexIndicator::None,
+            start_bit,
+            size,
+            is_little_endian: true,
+            is_signed: false,
+            value_type: ValueType::Integer,
+            factor: 1.0,
+            offset: 0.0,
+            value_min: 0.0,
+            value_max: 0.0,
+            unit: String::new(),
+            receivers: Vec::new(),
+            value_descriptions: HashMap::new(),
+            comment: None,
+            attributes: HashMap::new(),
+            extended_multiplex: None,
+            source_line: 0
+        }
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&AttributeValue> {
+        self.attributes.get(name)
+    }
+
+    // GenSigStartValue holds the raw value of the signal
+    pub fn start_value(&self) -> Option<i64> {
+        match self.attribute("GenSigStartValue") {
+            Some(AttributeValue::Int(value)) => Some(*value),
+            Some(AttributeValue::Float(value)) => Some(round(*value) as i64),
+            _ => None,
+        }
+    }
+
+    // Physical value to start from when nothing was received yet
+    pub fn default_value(&self) -> f64 {
+        match (self.start_value(), self.range()) {
+            (Some(raw), _) => self.raw_to_physical(raw),
+            (None, Some((min, max))) => 0.0f64.max(min).min(max),
+            (None, None) => 0.0,
+        }
+    }
+
+    pub fn raw_to_physical(&self, raw: i64) -> f64 {
+        raw as f64 * self.factor + self.offset
+    }
+
+    pub fn physical_to_raw(&self, physical: f64) -> i64 {
+        round((physical - self.offset) / self.factor) as i64
+    }
+
+    // Turns any text into a valid DBC identifier, e.g. "1 speed" into "_1_speed"
+    pub fn sanitize_name(name: &str) -> String {
+        let mut sanitized: String = name.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+            .collect();
+        if !sanitized.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            sanitized.insert(0, '_');
+        }
+        sanitized
+    }
+
+    pub fn description_for_raw(&self, raw: i64) -> Option<&str> {
+        self.value_descriptions.get(&raw).map(String::as_str)
+    }
+
+    // The lowest raw value wins when a description is used more than once
+    pub fn raw_for_description(&self, description: &str) -> Option<i64> {
+        self.value_descriptions.iter()
+            .filter(|(_, text)| text.as_str() == description)
+            .map(|(&raw, _)| raw)
+            .min()
+    }
+
+    pub fn byte_order(&self) -> ByteOrder {
+        if self.is_little_endian {
+            ByteOrder::LittleEndian
+        }
+        else {
+            ByteOrder::BigEndian
+        }
+    }
+
+    pub fn is_multiplexed(&self) -> bool {
+        matches!(self.multiplexer, MultiplexIndicator::MultiplexedBy(_)) || self.extended_multiplex.is_some()
+    }
+
+    pub fn range(&self) -> Option<(f64, f64)> {
+        // DBC files use [0|0] when no range is defined
+        if self.value_min == 0.0 && self.value_max == 0.0 {
+            None
+        }
+        else {
+            Some((self.value_min, self.value_max))
+        }
+    }
+
+    // The range of a DBC signal is given in physical units
+    pub fn physical_min(&self) -> f64 {
+        self.value_min
+    }
+
+    pub fn physical_max(&self) -> f64 {
+        self.value_max
+    }
+
+    // A negative factor swaps the ends of the range
+    pub fn raw_min(&self) -> i64 {
+        self.physical_to_raw(self.value_min).min(self.physical_to_raw(self.value_max))
+    }
+
+    pub fn raw_max(&self) -> i64 {
+        self.physical_to_raw(self.value_min).max(self.physical_to_raw(self.value_max))
+    }
+
+    pub fn msb_start_bit(&self) -> u16 {
+        // Position of the most significant bit in sequential numbering,
+        // where bit 0 is the most significant bit of the first byte
+        let msb = if self.is_little_endian {
+            u32::from(self.start_bit) + u32::from(self.size.saturating_sub(1))
+        }
+        else {
+            u32::from(self.start_bit)
+        };
+        u16::try_from(msb / 8 * 8 + 7 - msb % 8).unwrap_or(u16::MAX)
+    }
+
+    // Positions at or past MAX_FRAME_BITS cannot be part of any frame and are left out
+    pub fn bit_positions(&self) -> Vec<u16> {
+        let start = u32::from(self.start_bit);
+        if self.is_little_endian {
+            let end = (start + u32::from(self.size)).min(MAX_FRAME_BITS);
+            return (start.min(end)..end).map(|bit| bit as u16).collect();
+        }
+
+        let mut positions = Vec::with_capacity(usize::from(self.size));
+        let mut bit = start;
+        for _ in 0..self.size {
+            if bit < MAX_FRAME_BITS {
+                positions.push(bit as u16);
+            }
+            bit = if bit % 8 == 0 { bit + 15 } else { bit - 1 };
+        }
+        positions.reverse();
+
+        positions
+    }
+
+    pub fn overlaps(&self, other: &Signal) -> bool {
+        // Signals for different multiplexor values may share bits
+        if let (MultiplexIndicator::MultiplexedBy(a), MultiplexIndicator::MultiplexedBy(b)) =
+            (self.multiplexer, other.multiplexer) {
+            if a != b {
+                return false;
+            }
+        }
+
+        let bits = self.bit_positions();
+        other.bit_positions().iter().any(|bit| bits.contains(bit))
+    }
+
+    fn diff(&self, other: &Signal) -> SignalDiff {
+        let mut changes = Vec::new();
+        field_change(&mut changes, "multiplexer", &format!("{:?}", self.multiplexer), &format!("{:?}", other.multiplexer));
+        field_change(&mut changes, "start_bit", &self.start_bit, &other.start_bit);
+        field_change(&mut changes, "size", &self.size, &other.size);
+        field_change(&mut changes, "is_little_endian", &self.is_little_endian, &other.is_little_endian);
+        field_change(&mut changes, "is_signed", &self.is_signed, &other.is_signed);
+        field_change(&mut changes, "value_type", &format!("{:?}", self.value_type), &format!("{:?}", other.value_type));
+        field_change(&mut changes, "factor", &self.factor, &other.factor);
+        field_change(&mut changes, "offset", &self.offset, &other.offset);
+        field_change(&mut changes, "value_min", &self.value_min, &other.value_min);
+        field_change(&mut changes, "value_max", &self.value_max, &other.value_max);
+        field_change(&mut changes, "unit", &self.unit, &other.unit);
+        field_change(&mut changes, "receivers", &self.receivers.join(","), &other.receivers.join(","));
+
+        SignalDiff { name: self.name.clone(), changes }
+    }
+
+    pub fn mask(&self) -> u64 {
+        if self.size >= 64 {
+            u64::MAX
+        }
+        else {
+            (1 << self.size) - 1
+        }
+    }
+
+    // Shift of the signal inside the first 8 bytes of the frame read as a
+    // u64: little-endian for Intel signals and big-endian for Motorola ones
+    pub fn shift(&self) -> u32 {
+        if self.is_little_endian {
+            u32::from(self.start_bit)
+        }
+        else {
+            64u32.saturating_sub(u32::from(self.msb_start_bit()) + u32::from(self.size))
+        }
+    }
+
+    pub fn decode(&self, data: &[u8]) -> f64 {
+        let raw = self.raw_value(data);
+
+        let raw = match self.value_type {
+            ValueType::Float => f64::from(f32::from_bits(raw as u32)),
+            ValueType::Double => f64::from_bits(raw),
+            ValueType::Integer if self.is_signed && self.size > 0 => {
+                // Moving the sign bit to bit 63 and shifting back arithmetically
+                // extends the two's complement value, including full-width signals
+                let unused = 64 - self.size.min(64);
+                ((raw << unused) as i64 >> unused) as f64
+            },
+            ValueType::Integer => raw as f64,
+        };
+        raw * self.factor + self.offset
+    }
+
+    // Intel signals must start at bit 0 of a byte and Motorola
+    // signals at bit 7, spanning a whole number of bytes
+    pub fn is_byte_aligned(&self) -> bool {
+        let first_bit = if self.is_little_endian { 0 } else { 7 };
+        self.size > 0 && self.size % 8 == 0 && self.start_bit % 8 == first_bit
+    }
+
+    pub fn length_bytes(&self) -> Option<usize> {
+        if self.is_byte_aligned() {
+            Some(usize::from(self.size / 8))
+        }
+        else {
+            None
+        }
+    }
+
+    fn raw_value(&self, data: &[u8]) -> u64 {
+        // Whole bytes can be copied directly instead of walking each bit
+        if let Some(length) = self.length_bytes().filter(|&length| length <= 8) {
+            let first = usize::from(self.start_bit / 8);
+            if let Some(bytes) = data.get(first..first + length) {
+                let mut buffer = [0u8; 8];
+                return if self.is_little_endian {
+                    buffer[..length].copy_from_slice(bytes);
+                    u64::from_le_bytes(buffer)
+                }
+                else {
+                    buffer[8 - length..].copy_from_slice(bytes);
+                    u64::from_be_bytes(buffer)
+                };
+            }
+        }
+
+        self.raw_value_bitwise(data)
+    }
+
+    fn raw_value_bitwise(&self, data: &[u8]) -> u64 {
+        // Positions are computed in u32 so signals reaching past bit 65535
+        // do not overflow, bits outside of the data read as 0
+        let bit_at = |bit: u32| {
+            let byte = data.get((bit / 8) as usize).copied().unwrap_or(0);
+            u64::from(byte >> (bit % 8) & 1)
+        };
+
+        let mut raw = 0u64;
+        if self.is_little_endian {
+            // Bits past the 64th cannot be represented and are dropped
+            for i in 0..self.size.min(64) {
+                raw |= bit_at(u32::from(self.start_bit) + u32::from(i)) << i;
+            }
+        }
+        else {
+            // Motorola signals start at their most significant bit and
+            // continue from bit 7 of the next byte when a byte is exhausted
+            let mut bit = u32::from(self.start_bit);
+            for _ in 0..self.size {
+                raw = raw << 1 | bit_at(bit);
+                bit = if bit % 8 == 0 { bit + 15 } else { bit - 1 };
+            }
+        }
+
+        raw
+    }
+
+    pub fn encode(&self, physical: f64, data: &mut [u8]) {
+        let raw = (physical - self.offset) / self.factor;
+        match self.value_type {
+            ValueType::Float => return self.write_raw_value(u64::from((raw as f32).to_bits()), data),
+            ValueType::Double => return self.write_raw_value(raw.to_bits(), data),
+            ValueType::Integer => {},
+        }
+
+        let (min, max) = if self.is_signed {
+            (-pow2(i32::from(self.size) - 1), pow2(i32::from(self.size) - 1) - 1.0)
+        }
+        else {
+            (0.0, pow2(i32::from(self.size)) - 1.0)
+        };
+
+        let raw = round(raw).max(min).min(max);
+        let raw = if self.is_signed { raw as i64 as u64 } else { raw as u64 };
+        self.write_raw_value(raw, data);
+    }
+
+    pub fn encode_checked(&self, physical: f64, data: &mut [u8]) -> Result<(), EncodeError> {
+        if let Some((min, max)) = self.range() {
+            if !(min..=max).contains(&physical) {
+                return Err(EncodeError::OutOfRange { signal: self.name.clone(), value: physical, min, max });
+            }
+        }
+
+        self.encode(physical, data);
+        Ok(())
+    }
+
+    fn write_raw_value(&self, raw: u64, data: &mut [u8]) {
+        let mut set_bit = |bit: u32, value: u64| {
+            if let Some(byte) = data.get_mut((bit / 8) as usize) {
+                *byte &= !(1 << (bit % 8));
+                *byte |= ((value & 1) as u8) << (bit % 8);
+            }
+        };
+
+        // The raw value only has 64 bits, any further bits are cleared
+        let bit_of = |i: u16| raw.checked_shr(u32::from(i)).unwrap_or(0);
+        if self.is_little_endian {
+            for i in 0..self.size {
+                set_bit(u32::from(self.start_bit) + u32::from(i), bit_of(i));
+            }
+        }
+        else {
+            let mut bit = u32::from(self.start_bit);
+            for i in (0..self.size).rev() {
+                set_bit(bit, bit_of(i));
+                bit = if bit % 8 == 0 { bit + 15 } else { bit - 1 };
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+pub fn run(config: Config) -> Result<(), DbcError> {
+    for (i, filename) in config.filenames.iter().enumerate() {
+        let mut dbc = read_file(Path::new(filename))?;
+        if let Some((low, high)) = config.id_range {
+            dbc.retain_id_range(low, high);
+        }
+        match config.format {
+            OutputFormat::Debug => println!("{:?}", dbc),
+            #[cfg(feature = "serde")]
+            OutputFormat::Json => println!("{}", serde_json::to_string(&dbc)?),
+            OutputFormat::Csv => {
+                // A single header row for all the files
+                let csv = dbc.to_signal_csv();
+                let rows = if i == 0 { csv.as_str() } else { csv.split_once('\n').map_or("", |(_, rows)| rows) };
+                print!("{}", rows);
+            },
+        }
+    }
+
+    Ok(())
+}
+
+// The error is always a DbcError, which can be recovered with downcast_ref
+#[cfg(feature = "std")]
+pub fn parse_file<P: AsRef<Path>>(path: P) -> Result<Dbc, Box<dyn Error>> {
+    Ok(read_file(path.as_ref())?)
+}
+
+#[cfg(feature = "std")]
+fn read_file(path: &Path) -> Result<Dbc, DbcError> {
+    let contents = fs::read(path)?;
+    // Compressed files are recognized by the gzip magic bytes, whatever their extension
+    #[cfg(feature = "flate2")]
+    let contents = if contents.starts_with(&[0x1f, 0x8b]) {
+        let mut decompressed = Vec::new();
+        flate2::read::MultiGzDecoder::new(contents.as_slice()).read_to_end(&mut decompressed)?;
+        decompressed
+    }
+    else {
+        contents
+    };
+    parse_bytes(&contents)
+}
+
+// Content that is not valid UTF-8 is read as Latin-1, the encoding
+// of many older DBC files
+pub fn parse_bytes(contents: &[u8]) -> Result<Dbc, DbcError> {
+    match core::str::from_utf8(contents) {
+        Ok(contents) => parse(contents),
+        Err(_) => {
+            let contents: String = contents.iter().map(|&byte| char::from(byte)).collect();
+            parse(&contents)
+        },
+    }
+}
+
+pub fn parse(contents: &str) -> Result<Dbc, DbcError> {
+    let mut collector = DbcCollector::default();
+    parse_lines(str_lines(contents), &mut collector)?;
+
+    Ok(collector.into_dbc())
+}
+
+// Lines that parse only reports as warnings are errors here
+pub fn parse_strict(contents: &str) -> Result<Dbc, DbcError> {
+    let dbc = parse(contents)?;
+    match dbc.warnings.first() {
+        Some(warning) => Err(DbcError::InvalidLine {
+            line: warning.line, content: warning.content.clone(), reason: "Unsupported line"
+        }),
+        None => Ok(dbc),
+    }
+}
+
+#[cfg(feature = "std")]
+pub fn parse_reader<R: BufRead>(reader: R) -> Result<Dbc, DbcError> {
+    let mut collector = DbcCollector::default();
+    parse_lines(reader.lines(), &mut collector)?;
+
+    Ok(collector.into_dbc())
+}
+
+pub fn parse_with_visitor<V: DbcVisitor>(contents: &str, visitor: &mut V) -> Result<(), DbcError> {
+    parse_lines(str_lines(contents), &mut VisitorSink(visitor))
+}
+
+// Lines of content that is already in memory, reading them cannot fail
+fn str_lines(contents: &str) -> impl Iterator<Item = Result<String, DbcError>> + '_ {
+    contents.lines().map(|line| Ok(line.to_string()))
+}
+
+// What parse_lines reports to. Visitors only get to see the parsed elements,
+// while the collector also takes each message over with its signals once
+// the message block has ended, instead of copying them
+trait LineSink {
+    type Visitor: DbcVisitor;
+
+    fn visitor(&mut self) -> &mut Self::Visitor;
+    fn end_message(&mut self, _message: Message, _signals: Vec<Signal>) {}
+}
+
+struct VisitorSink<'a, V>(&'a mut V);
+
+impl<V: DbcVisitor> LineSink for VisitorSink<'_, V> {
+    type Visitor = V;
+
+    fn visitor(&mut self) -> &mut V {
+        self.0
+    }
+}
+
+// The parse loop behind parse_reader and parse_with_visitor. It only keeps
+// what is needed to make sense of later lines and hands everything else
+// over to the sink
+fn parse_lines<I, E, S>(lines: I, sink: &mut S) -> Result<(), DbcError>
+where I: Iterator<Item = Result<String, E>>, DbcError: From<E>, S: LineSink {
+    let mut node_names: Vec<String> = Vec::new();
+    // The message whose signals are being read, and those signals
+    let mut message: Option<(Message, Vec<Signal>)> = None;
+    let mut value_tables: HashMap<String, HashMap<i64, String>> = HashMap::new();
+    let mut attribute_definitions: Vec<AttributeDefinition> = Vec::new();
+
+    let mut in_new_symbols = false;
+    for line in LogicalLines::new(lines) {
+        let (i, line) = line?;
+        let line = line.as_str();
+
+        let keyword = first_keyword(line);
+        if in_new_symbols && is_new_symbol(line) {
+            continue;
+        }
+        in_new_symbols = keyword == Some("NS_");
+        let is_unsupported = keyword.is_some_and(|keyword| UNSUPPORTED_KEYWORDS.contains(&keyword));
+        if line.trim().is_empty() || is_unsupported
+            || keyword.is_some_and(|keyword| IGNORED_KEYWORDS.contains(&keyword)) {
+            // A blank or unparsed line also ends the message block
+            if let Some((ended, signals)) = message.take() {
+                sink.end_message(ended, signals);
+            }
+            if is_unsupported {
+                sink.visitor().on_warning(&ParseWarning { line: i+1, content: line.to_string() });
+            }
+            continue;
+        }
+
+        if let Some((current, signals)) = &mut message {
+            match parse_type::<Signal>(line) {
+                Ok(mut signal) => {
+                    signal.source_line = i+1;
+                    sink.visitor().on_signal(current, &signal);
+                    signals.push(signal);
+                    continue;
+                },
+                Err(DbcError::WrongType) => {
+                    // In this case, the message block ended and
+                    // the line is parsed as any other
+                    if let Some((ended, signals)) = message.take() {
+                        sink.end_message(ended, signals);
+                    }
+                },
+                Err(error) => {
+                    // A line with the right syntax was only rejected for its position
+                    let reason = if HASHMAP.get(Signal::REGEX).unwrap().is_match(line.trim()) {
+                        "Signal does not fit in the frame"
+                    }
+                    else {
+                        invalid_field(line, &SIGNAL_FIELDS)
+                    };
+                    return Err(line_error(error, i, line, reason));
+                },
+            }
+        }
+
+        let mut recognized = false;
+
+        match parse_type::<Version>(line) {
+            Ok(Version(text)) => {
+                recognized = true;
+                sink.visitor().on_version(&text);
+            },
+            Err(DbcError::WrongType) => {},
+            Err(error) => return Err(line_error(error, i, line, "Invalid version")),
+        }
+
+        match parse_type::<BitTiming>(line) {
+            Ok(BitTiming(rate)) => {
+                recognized = true;
+                sink.visitor().on_baudrate(rate);
+            },
+            Err(DbcError::WrongType) => {},
+            Err(error) => return Err(line_error(error, i, line, "Invalid bit timing")),
+        }
+
+        match parse_type_vec::<Node>(line) {
+            Ok(new_nodes) => {
+                recognized = true;
+                // Several node lists are merged, keeping the first spelling of each node
+                for node in new_nodes {
+                    if !node_names.iter().any(|name| name.eq_ignore_ascii_case(&node.name)) {
+                        node_names.push(node.name.clone());
+                        sink.visitor().on_node(&node);
+                    }
+                }
+            },
+            Err(DbcError::WrongType) => {},
+            Err(error) => return Err(line_error(error, i, line, "Invalid syntax for nodes")),
+        }
+        
+        match parse_type::<Message>(line) {
+            Ok(mut new_message) => {
+                recognized = true;
+                new_message.source_line = i+1;
+                sink.visitor().on_message(&new_message);
+                message = Some((new_message, Vec::new()));
+            },
+            Err(DbcError::WrongType) => {},
+            Err(error) => return Err(line_error(error, i, line, invalid_field(line, &MESSAGE_FIELDS))),
+        }
+
+        match parse_type::<ValueTable>(line) {
+            Ok(value_table) => {
+                recognized = true;
+                sink.visitor().on_value_table(&value_table.name, &value_table.descriptions);
+                value_tables.insert(value_table.name, value_table.descriptions);
+            },
+            Err(DbcError::WrongType) => {},
+            Err(error) => return Err(line_error(error, i, line, "Invalid value table")),
+        }
+
+        match parse_type::<ValueDescriptions>(line) {
+            Ok(ValueDescriptions { message_id, signal_name, table, descriptions }) => {
+                recognized = true;
+                // Descriptions taken from unknown value tables are skipped
+                let descriptions = match table {
+                    Some(table) => value_tables.get(&table),
+                    None => Some(&descriptions),
+                };
+                if let Some(descriptions) = descriptions {
+                    sink.visitor().on_value_descriptions(message_id, &signal_name, descriptions);
+                }
+            },
+            Err(DbcError::WrongType) => {},
+            Err(error) => return Err(line_error(error, i, line, "Invalid value description")),
+        }
+
+        match parse_type::<SignalMultiplexValues>(line) {
+            Ok(multiplex_values) => {
+                recognized = true;
+                sink.visitor().on_extended_multiplex(
+                    multiplex_values.message_id, &multiplex_values.signal_name, &multiplex_values.multiplex
+                );
+            },
+            Err(DbcError::WrongType) => {},
+            Err(error) => return Err(line_error(error, i, line, "Invalid extended multiplexing")),
+        }
+
+        match parse_type::<SignalValueType>(line) {
+            Ok(SignalValueType { message_id, signal_name, value_type }) => {
+                recognized = true;
+                sink.visitor().on_value_type(message_id, &signal_name, value_type);
+            },
+            Err(DbcError::WrongType) => {},
+            Err(error) => return Err(line_error(error, i, line, "Invalid signal value type")),
+        }
+
+        match parse_type::<MessageTransmitters>(line) {
+            Ok(message_transmitters) => {
+                recognized = true;
+                sink.visitor().on_transmitters(message_transmitters.message_id, &message_transmitters.transmitters);
+            },
+            Err(DbcError::WrongType) => {},
+            Err(error) => return Err(line_error(error, i, line, "Invalid message transmitters")),
+        }
+
+        match parse_type::<AttributeDefinition>(line) {
+            Ok(definition) => {
+                recognized = true;
+                sink.visitor().on_attribute_definition(&definition);
+                attribute_definitions.push(definition);
+            },
+            Err(DbcError::WrongType) => {},
+            Err(error) => return Err(line_error(error, i, line, "Invalid attribute definition")),
+        }
+
+        match parse_type::<AttributeDefault>(line) {
+            Ok(default) => {
+                recognized = true;
+                // Defaults for unknown attributes are skipped
+                let definition = attribute_definitions.iter()
+                    .find(|definition| definition.name == default.name);
+                if let Some(definition) = definition {
+                    sink.visitor().on_attribute_default(&default.name, &attribute_value(&default.value, Some(definition)));
+                }
+            },
+            Err(DbcError::WrongType) => {},
+            Err(error) => return Err(line_error(error, i, line, "Invalid attribute default")),
+        }
+
+        match parse_type::<AttributeAssignment>(line) {
+            Ok(assignment) => {
+                recognized = true;
+                let definition = attribute_definitions.iter()
+                    .find(|definition| definition.name == assignment.name);
+                let value = assignment.value(definition);
+                let name = &assignment.name;
+                match &assignment.target {
+                    AttributeTarget::Global => sink.visitor().on_attribute(name, &value),
+                    AttributeTarget::Node(node) => sink.visitor().on_node_attribute(node, name, &value),
+                    AttributeTarget::Message(id) => sink.visitor().on_message_attribute(*id, name, &value),
+                    AttributeTarget::Signal(message_id, signal_name) => {
+                        sink.visitor().on_signal_attribute(*message_id, signal_name, name, &value);
+                    },
+                    AttributeTarget::EnvVar => {},
+                }
+            },
+            Err(DbcError::WrongType) => {},
+            Err(error) => return Err(line_error(error, i, line, "Invalid attribute value")),
+        }
+
+        match parse_type::<EnvVar>(line) {
+            Ok(env_var) => {
+                recognized = true;
+                sink.visitor().on_env_var(&env_var);
+            },
+            Err(DbcError::WrongType) => {},
+            Err(error) => return Err(line_error(error, i, line, "Invalid environment variable")),
+        }
+
+        match parse_type::<MessageSignalGroup>(line) {
+            Ok(signal_group) => {
+                recognized = true;
+                sink.visitor().on_signal_group(signal_group.message_id, &signal_group.group);
+            },
+            Err(DbcError::WrongType) => {},
+            Err(error) => return Err(line_error(error, i, line, "Invalid signal group")),
+        }
+
+        match parse_type::<Comment>(line) {
+            Ok(comment) => {
+                recognized = true;
+                match comment {
+                    Comment::Message { id, text } => sink.visitor().on_message_comment(id, &text),
+                    Comment::Signal { message_id, signal_name, text } => {
+                        sink.visitor().on_signal_comment(message_id, &signal_name, &text);
+                    },
+                    Comment::Node { name, text } => sink.visitor().on_node_comment(&name, &text),
+                    Comment::Other => {},
+                }
+            },
+            Err(DbcError::WrongType) => {},
+            Err(error) => return Err(line_error(error, i, line, "Invalid comment")),
+        }
+
+        if !recognized {
+            sink.visitor().on_warning(&ParseWarning { line: i+1, content: line.to_string() });
+        }
+    }
+
+    if let Some((ended, signals)) = message {
+        sink.end_message(ended, signals);
+    }
+
+    Ok(())
+}
+
+impl LineSink for DbcCollector {
+    type Visitor = Self;
+
+    fn visitor(&mut self) -> &mut Self {
+        self
+    }
+
+    fn end_message(&mut self, mut message: Message, signals: Vec<Signal>) {
+        message.signals = signals;
+        self.messages.push(message);
+    }
+}
+
+impl DbcVisitor for DbcCollector {
+    fn on_version(&mut self, version: &str) {
+        self.version = Some(version.to_string()).filter(|version| !version.is_empty());
+    }
+
+    fn on_baudrate(&mut self, baudrate: Option<u32>) {
+        self.baudrate = baudrate;
+    }
+
+    fn on_node(&mut self, node: &Node) {
+        self.nodes.push(node.clone());
+    }
+
+    // Messages and their signals are taken over in LineSink::end_message
+
+    fn on_value_table(&mut self, name: &str, descriptions: &HashMap<i64, String>) {
+        self.value_tables.insert(name.to_string(), descriptions.clone());
+    }
+
+    // Anything referring to unknown messages or signals is skipped
+    fn on_value_descriptions(&mut self, message_id: u32, signal_name: &str, descriptions: &HashMap<i64, String>) {
+        if let Some(signal) = self.signal_mut(message_id, signal_name) {
+            signal.value_descriptions = descriptions.clone();
+        }
+    }
+
+    fn on_extended_multiplex(&mut self, message_id: u32, signal_name: &str, multiplex: &ExtendedMultiplex) {
+        if let Some(signal) = self.signal_mut(message_id, signal_name) {
+            signal.extended_multiplex = Some(multiplex.clone());
+        }
+    }
+
+    fn on_value_type(&mut self, message_id: u32, signal_name: &str, value_type: ValueType) {
+        if let Some(signal) = self.signal_mut(message_id, signal_name) {
+            signal.value_type = value_type;
+        }
+    }
+
+    fn on_transmitters(&mut self, message_id: u32, transmitters: &[String]) {
+        if let Some(message) = self.message_mut(message_id) {
+            for transmitter in transmitters {
+                if !message.transmitters.contains(transmitter) {
+                    message.transmitters.push(transmitter.clone());
+                }
+            }
+        }
+    }
+
+    fn on_signal_group(&mut self, message_id: u32, group: &SignalGroup) {
+        if let Some(message) = self.message_mut(message_id) {
+            message.signal_groups.push(group.clone());
+        }
+    }
+
+    fn on_attribute_definition(&mut self, definition: &AttributeDefinition) {
+        self.attribute_definitions.push(definition.clone());
+    }
+
+    fn on_attribute_default(&mut self, name: &str, value: &AttributeValue) {
+        let definition = self.attribute_definitions.iter_mut()
+            .find(|definition| definition.name == name);
+        if let Some(definition) = definition {
+            definition.default = Some(value.clone());
+        }
+    }
+
+    fn on_attribute(&mut self, name: &str, value: &AttributeValue) {
+        self.attributes.insert(name.to_string(), value.clone());
+    }
+
+    fn on_node_attribute(&mut self, node: &str, name: &str, value: &AttributeValue) {
+        self.node_attributes.entry(node.to_string()).or_default().insert(name.to_string(), value.clone());
+    }
+
+    fn on_message_attribute(&mut self, message_id: u32, name: &str, value: &AttributeValue) {
+        if let Some(message) = self.message_mut(message_id) {
+            message.attributes.insert(name.to_string(), value.clone());
+        }
+    }
+
+    fn on_signal_attribute(&mut self, message_id: u32, signal_name: &str, name: &str, value: &AttributeValue) {
+        if let Some(signal) = self.signal_mut(message_id, signal_name) {
+            signal.attributes.insert(name.to_string(), value.clone());
+        }
+    }
+
+    fn on_env_var(&mut self, env_var: &EnvVar) {
+        self.env_vars.push(env_var.clone());
+    }
+
+    fn on_message_comment(&mut self, message_id: u32, text: &str) {
+        if let Some(message) = self.message_mut(message_id) {
+            message.comment = Some(text.to_string());
+        }
+    }
+
+    fn on_signal_comment(&mut self, message_id: u32, signal_name: &str, text: &str) {
+        if let Some(signal) = self.signal_mut(message_id, signal_name) {
+            signal.comment = Some(text.to_string());
+        }
+    }
+
+    fn on_node_comment(&mut self, node: &str, text: &str) {
+        // Nodes keep the first spelling of their name, see parse_lines
+        if let Some(node) = self.nodes.iter_mut().find(|existing| existing.name.eq_ignore_ascii_case(node)) {
+            node.comment = Some(text.to_string());
+        }
+    }
+
+    fn on_warning(&mut self, warning: &ParseWarning) {
+        self.warnings.push(warning.clone());
+    }
+}
+
+impl DbcCollector {
+    fn message_mut(&mut self, id: u32) -> Option<&mut Message> {
+        self.messages.iter_mut().find(|message| message.id == id)
+    }
+
+    fn signal_mut(&mut self, message_id: u32, signal_name: &str) -> Option<&mut Signal> {
+        self.message_mut(message_id).and_then(|message| message.signal_by_name_mut(signal_name))
+    }
+
+    fn into_dbc(mut self) -> Dbc {
+        // Objects without an explicit value take the default of the attribute
+        for definition in &self.attribute_definitions {
+            let Some(default) = &definition.default else {
+                continue;
+            };
+            let name = &definition.name;
+            match definition.object {
+                AttributeObject::Global => {
+                    self.attributes.entry(name.clone()).or_insert_with(|| default.clone());
+                },
+                AttributeObject::Node => {
+                    for node in &self.nodes {
+                        self.node_attributes.entry(node.name.clone()).or_default()
+                            .entry(name.clone()).or_insert_with(|| default.clone());
+                    }
+                },
+                AttributeObject::Message => {
+                    for message in &mut self.messages {
+                        message.attributes.entry(name.clone()).or_insert_with(|| default.clone());
+                    }
+                },
+                AttributeObject::Signal => {
+                    for signal in self.messages.iter_mut().flat_map(|message| message.signals.iter_mut()) {
+                        signal.attributes.entry(name.clone()).or_insert_with(|| default.clone());
+                    }
+                },
+                AttributeObject::EnvVar => {},
+            }
+        }
+
+        let mut dbc = Dbc::new(self.nodes, self.messages);
+        dbc.version = self.version;
+        dbc.baudrate = self.baudrate;
+        dbc.warnings = self.warnings;
+        dbc.attribute_definitions = self.attribute_definitions;
+        dbc.attributes = self.attributes;
+        dbc.node_attributes = self.node_attributes;
+        dbc.env_vars = self.env_vars;
+        dbc.value_tables = self.value_tables;
+
+        dbc
+    }
+}
+
+impl fmt::Display for DbcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DbcError::WrongType => write!(f, "wrong type"),
+            DbcError::InvalidContent => write!(f, "invalid content"),
+            DbcError::InvalidLine { line, content, reason } => {
+                write!(f, "Error when parsing line {}: {}. {}.", line, content, reason)
+            },
+            DbcError::IoError(error) => write!(f, "I/O error: {}", error),
+            DbcError::SerializationError(error) => write!(f, "Serialization error: {}", error),
+            DbcError::NumberParse { line, field } => {
+                write!(f, "Error when parsing line {}: invalid number for {}.", line, field)
+            },
+            DbcError::UnknownReference(reference) => write!(f, "Unknown reference to {}", reference),
+            DbcError::DuplicateMessageId(id) => write!(f, "Duplicate message id {}", id),
+            DbcError::DuplicateSignalName(name) => write!(f, "Duplicate signal name {}", name),
+        }
+    }
+}
+
+impl Error for DbcError {}
+
+impl NameAudit {
+    pub fn is_empty(&self) -> bool {
+        self.duplicate_message_names.is_empty() && self.duplicate_signal_names.is_empty()
+            && self.duplicate_message_ids.is_empty()
+    }
+}
+
+impl DbcDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_messages.is_empty() && self.removed_messages.is_empty() && self.changed_messages.is_empty()
+    }
+}
+
+impl MessageDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.added_signals.is_empty()
+            && self.removed_signals.is_empty() && self.changed_signals.is_empty()
+    }
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EncodeError::OutOfRange { signal, value, min, max } => {
+                write!(f, "Value {} for signal {} is outside of [{}|{}]", value, signal, min, max)
+            },
+        }
+    }
+}
+
+impl Error for EncodeError {}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Line {} was not parsed: {}", self.line, self.content)
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationError::SignalOutOfBounds { message, signal } => {
+                write!(f, "Signal {} exceeds the size of message {}", signal, message)
+            },
+            ValidationError::DuplicateMessageId { id, first, second } => {
+                write!(f, "Messages {} and {} share the id {}", first, second, id)
+            },
+            ValidationError::OverlappingSignals { message, first, second } => {
+                write!(f, "Signals {} and {} overlap in message {}", first, second, message)
+            },
+        }
+    }
+}
+
+impl fmt::Display for AttributeDefinition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let object = match self.object {
+            AttributeObject::Global => "",
+            AttributeObject::Node => "BU_ ",
+            AttributeObject::Message => "BO_ ",
+            AttributeObject::Signal => "SG_ ",
+            AttributeObject::EnvVar => "EV_ "
+        };
+        write!(f, "BA_DEF_ {}\"{}\" ", object, self.name)?;
+
+        match &self.value_type {
+            AttributeType::Int { min, max } => write!(f, "INT {} {};", min, max),
+            AttributeType::Hex { min, max } => write!(f, "HEX {} {};", min, max),
+            AttributeType::Float { min, max } => write!(f, "FLOAT {} {};", min, max),
+            AttributeType::String => write!(f, "STRING ;"),
+            AttributeType::Enum(values) => {
+                let values: Vec<String> = values.iter().map(|value| format!("\"{}\"", value)).collect();
+                write!(f, "ENUM {};", values.join(","))
+            },
+        }
+    }
+}
+
+impl fmt::Display for AttributeValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AttributeValue::Int(value) => write!(f, "{}", value),
+            AttributeValue::Float(value) => write!(f, "{}", value),
+            AttributeValue::String(value) => write!(f, "\"{}\"", value),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for DbcError {
+    fn from(error: io::Error) -> Self {
+        DbcError::IoError(error.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<serde_json::Error> for DbcError {
+    fn from(error: serde_json::Error) -> Self {
+        DbcError::SerializationError(error.to_string())
+    }
+}
+
+impl FromStr for Dbc {
+    type Err = DbcError;
+
+    fn from_str(contents: &str) -> Result<Self, Self::Err> {
+        parse(contents)
+    }
+}
+
+impl<'a> IntoIterator for &'a Dbc {
+    type Item = &'a Message;
+    type IntoIter = core::slice::Iter<'a, Message>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.messages.iter()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Exporter for JsonExporter {
+    fn export(&self, dbc: &Dbc) -> String {
+        // Every key of the database is a string or an integer,
+        // so the conversion cannot fail
+        dbc.to_json().expect("database is always serializable")
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Exporter for CantoolsExporter {
+    fn export(&self, dbc: &Dbc) -> String {
+        let messages: Vec<serde_json::Value> = dbc.messages.iter()
+            .map(|message| serde_json::json!({
+                "name": message.name,
+                "frame_id": message.can_id(),
+                "is_extended_frame": message.is_extended(),
+                "length": message.size,
+                "senders": message.transmitters,
+                "signals": message.signals.iter().map(|signal| serde_json::json!({
+                    "name": signal.name,
+                    "start": signal.start_bit,
+                    "length": signal.size,
+                    "byte_order": match signal.byte_order() {
+                        ByteOrder::LittleEndian => "little_endian",
+                        ByteOrder::BigEndian => "big_endian"
+                    },
+                    "is_signed": signal.is_signed,
+                    "scale": signal.factor,
+                    "offset": signal.offset,
+                    "minimum": signal.range().map(|(min, _)| min),
+                    "maximum": signal.range().map(|(_, max)| max),
+                    "unit": signal.unit
+                })).collect::<Vec<_>>()
+            }))
+            .collect();
+
+        serde_json::json!({ "messages": messages }).to_string()
+    }
+}
+
+impl Exporter for CsvExporter {
+    fn export(&self, dbc: &Dbc) -> String {
+        dbc.to_signal_csv()
+    }
+}
+
+impl Exporter for DbcExporter {
+    fn export(&self, dbc: &Dbc) -> String {
+        dbc.to_dbc_string()
+    }
+}
+
+// The lookup index and the parse warnings describe where the
+// content came from, so they are not part of the comparison
+impl PartialEq for Dbc {
+    fn eq(&self, other: &Self) -> bool {
+        self.version == other.version
+            && self.baudrate == other.baudrate
+            && self.nodes == other.nodes
+            && self.messages == other.messages
+            && self.attribute_definitions == other.attribute_definitions
+            && self.attributes == other.attributes
+            && self.node_attributes == other.node_attributes
+            && self.env_vars == other.env_vars
+            && self.value_tables == other.value_tables
+    }
+}
+
+// The source line only tells where an element was read from
+impl PartialEq for Message {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+            && self.name == other.name
+            && self.size == other.size
+            && self.transmitters == other.transmitters
+            && self.signals == other.signals
+            && self.signal_groups == other.signal_groups
+            && self.comment == other.comment
+            && self.attributes == other.attributes
+    }
+}
+
+impl PartialEq for Signal {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.multiplexer == other.multiplexer
+            && self.start_bit == other.start_bit
+            && self.size == other.size
+            && self.is_little_endian == other.is_little_endian
+            && self.is_signed == other.is_signed
+            && self.factor == other.factor
+            && self.offset == other.offset
+            && self.value_min == other.value_min
+            && self.value_max == other.value_max
+            && self.unit == other.unit
+            && self.receivers == other.receivers
+            && self.value_descriptions == other.value_descriptions
+            && self.comment == other.comment
+            && self.attributes == other.attributes
+            && self.extended_multiplex == other.extended_multiplex
+            && self.value_type == other.value_type
+    }
+}
+
+impl fmt::Display for EnvVar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let var_type = match self.var_type {
+            EnvVarType::Integer => 0,
+            EnvVarType::Float => 1,
+            EnvVarType::String => 2,
+        };
+        let access_type = match self.access_type {
+            EnvVarAccess::Unrestricted => 0,
+            EnvVarAccess::Read => 1,
+            EnvVarAccess::Write => 2,
+            EnvVarAccess::ReadWrite => 3,
+        };
+        // String variables carry the 0x8000 flag in the access type
+        let string_flag = if self.var_type == EnvVarType::String { "800" } else { "" };
+        let access_nodes = if self.access_nodes.is_empty() {
+            "Vector__XXX".to_string()
+        }
+        else {
+            self.access_nodes.join(",")
+        };
+
+        write!(f, "EV_ {}: {} [{}|{}] \"{}\" {} {} DUMMY_NODE_VECTOR{}{} {};",
+            self.name, var_type, self.minimum, self.maximum, escape(&self.unit),
+            self.initial_value, self.id, string_flag, access_type, access_nodes)
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "BO_ {} {}: {} {}", self.id, self.name, self.size,
+            self.transmitters.first().map(String::as_str).unwrap_or("Vector__XXX"))?;
+        for signal in &self.signals {
+            writeln!(f, " {}", signal)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let multiplexer = match self.multiplexer {
+            MultiplexIndicator::None => String::new(),
+            MultiplexIndicator::Multiplexor => " M".to_string(),
+            MultiplexIndicator::MultiplexedBy(value) => format!(" m{}", value)
+        };
+        let receivers = if self.receivers.is_empty() {
+            "Vector__XXX".to_string()
+        }
+        else {
+            self.receivers.join(",")
+        };
+
+        write!(f, "SG_ {}{} : {}|{}@{}{} ({},{}) [{}|{}] \"{}\" {}",
+            self.name, multiplexer, self.start_bit, self.size,
+            if self.is_little_endian { 1 } else { 0 },
+            if self.is_signed { '-' } else { '+' },
+            self.factor, self.offset, self.value_min, self.value_max,
+            escape(&self.unit), receivers)
+    }
+}
+
+impl DbcType for Node {
+    const TAG: &'static str = "BU_";
+    const REGEX: &'static str = r"^([A-Za-z_][A-Za-z0-9_]*)$";
+
+    fn from(cap: &Captures) -> Result<Self, DbcError> {
+        Ok(Node { 
+            name: cap[1].to_string(),
+            comment: None
+        })
+    }
+}
+
+impl DbcType for Message {
+    const TAG: &'static str = "BO_ ";
+    const REGEX: &'static str = r"BO_ (\d+) ([A-Za-z_][A-Za-z0-9_]*) *: (\d+) ([A-Za-z_][A-Za-z0-9_]*).*";
+
+    fn from(cap: &Captures) -> Result<Self, DbcError> {
+        Ok(Message { 
+            id: parse_number(&cap[1], "message id")?,
+            name: cap[2].to_string(),
+            size: parse_number(&cap[3], "message size")?,
+            transmitters: match &cap[4] {
+                "Vector__XXX" => Vec::new(),
+                transmitter => vec![transmitter.to_string()]
+            },
+            signals: Vec::new(),
+            signal_groups: Vec::new(),
+            comment: None,
+            attributes: HashMap::new(),
+            source_line: 0
+        })
+    }
+}
+
+impl DbcType for Signal {
+    const TAG: &'static str = "SG_";
+    // Tokens may be separated by any run of spaces or tabs
+    const REGEX: &'static str = concat!(
+        r"^SG_\s+([A-Za-z_][A-Za-z0-9_]*)(?:\s+(M|m\d+))?\s*:\s*(\d+)\|(\d+)@([01])\s*([\+|\-])\s*",
+        r"\(([-+]?(?:\d+\.?\d*|\.\d+)(?:[eE][-+]?\d+)?),([-+]?(?:\d+\.?\d*|\.\d+)(?:[eE][-+]?\d+)?)\)\s*",
+        r"\[([-+]?(?:\d+\.?\d*|\.\d+)(?:[eE][-+]?\d+)?)\|([-+]?(?:\d+\.?\d*|\.\d+)(?:[eE][-+]?\d+)?)\]\s*",
+        r#""((?:[^"\\]|\\.)*)"\s+(.*)"#
+    );
+
+    fn from(cap: &Captures) -> Result<Self, DbcError> {
+        let start_bit: u16 = parse_number(&cap[3], "start bit")?;
+        let size: u16 = parse_number(&cap[4], "signal size")?;
+        let is_little_endian = &cap[5] == "1";
+        // The signal has to fit in the largest frame, counting its bits in
+        // the order they are laid out from the first one
+        let first_bit = if is_little_endian {
+            u32::from(start_bit)
+        }
+        else {
+            u32::from(start_bit) / 8 * 8 + 7 - u32::from(start_bit) % 8
+        };
+        if first_bit + u32::from(size) > MAX_FRAME_BITS {
+            return Err(DbcError::InvalidContent);
+        }
+
+        Ok(Signal { 
+            name: cap[1].to_string(),
+            multiplexer: match cap.get(2).map(|m| m.as_str()) {
+                Some("M") => MultiplexIndicator::Multiplexor,
+                Some(m) => MultiplexIndicator::MultiplexedBy(parse_number(&m[1..], "multiplexor value")?),
+                None => MultiplexIndicator::None
+            },
+            start_bit,
+            size,
+            is_little_endian,
+            is_signed: cap[6].to_string() == "-",
+            value_type: ValueType::Integer,
+            factor: parse_number(&cap[7], "factor")?,
+            offset: parse_number(&cap[8], "offset")?,
+            value_min: parse_number(&cap[9], "minimum value")?,
+            value_max: parse_number(&cap[10], "maximum value")?,
+            unit: unescape(&cap[11]),
+            receivers: cap[12].split(',')
+                .map(|receiver| receiver.trim())
+                .filter(|receiver| !receiver.is_empty() && *receiver != "Vector__XXX")
+                .map(|receiver| receiver.to_string())
+                .collect(),
+            value_descriptions: HashMap::new(),
+            comment: None,
+            attributes: HashMap::new(),
+            extended_multiplex: None,
+            source_line: 0
+        })
+    }
+}
+
+impl DbcType for Comment {
+    const TAG: &'static str = "CM_ ";
+    const REGEX: &'static str = r#"(?s)^CM_ (?:BO_ (\d+) |SG_ (\d+) (\w+) |BU_ (\w+) |EV_ \w+ )?"(.*)"\s*;$"#;
+
+    fn from(cap: &Captures) -> Result<Self, DbcError> {
+        let text = unescape(&cap[5]);
+        if let Some(id) = cap.get(1) {
+            Ok(Comment::Message { id: parse_number(id.as_str(), "message id")?, text })
+        }
+        else if let Some(message_id) = cap.get(2) {
+            Ok(Comment::Signal {
+                message_id: parse_number(message_id.as_str(), "message id")?,
+                signal_name: cap[3].to_string(),
+                text
+            })
+        }
+        else if let Some(name) = cap.get(4) {
+            Ok(Comment::Node { name: name.as_str().to_string(), text })
+        }
+        else {
+            Ok(Comment::Other)
+        }
+    }
+}
+
+impl DbcType for Version {
+    const TAG: &'static str = "VERSION ";
+    const REGEX: &'static str = r#"^VERSION\s+"(.*)"$"#;
+
+    fn from(cap: &Captures) -> Result<Self, DbcError> {
+        Ok(Version(cap[1].to_string()))
+    }
+}
+
+impl DbcType for BitTiming {
+    const TAG: &'static str = "BS_";
+    const REGEX: &'static str = r"^BS_\s*:?\s*(?:(\d+)(?:\s*:\s*\d+\s*,\s*\d+)?)?\s*;?$";
+
+    fn from(cap: &Captures) -> Result<Self, DbcError> {
+        Ok(BitTiming(cap.get(1).map(|baudrate| parse_number(baudrate.as_str(), "baudrate")).transpose()?))
+    }
+}
+
+impl DbcType for MessageTransmitters {
+    const TAG: &'static str = "BO_TX_BU_ ";
+    const REGEX: &'static str = r"^BO_TX_BU_ (\d+)\s*:\s*([\w,\s]*);$";
+
+    fn from(cap: &Captures) -> Result<Self, DbcError> {
+        Ok(MessageTransmitters {
+            message_id: parse_number(&cap[1], "message id")?,
+            transmitters: cap[2].split(',')
+                .map(|transmitter| transmitter.trim())
+                .filter(|transmitter| !transmitter.is_empty())
+                .map(|transmitter| transmitter.to_string())
+                .collect()
+        })
+    }
+}
+
+impl DbcType for AttributeDefinition {
+    const TAG: &'static str = "BA_DEF_ ";
+    const REGEX: &'static str = concat!(
+        r#"^BA_DEF_ +(?:(BU_|BO_|SG_|EV_) +)?"(\w+)" +"#,
+        r#"(?:(INT|HEX) +(-?\d+) +(-?\d+)"#,
+        r#"|(FLOAT) +([-+]?(?:\d+\.?\d*|\.\d+)(?:[eE][-+]?\d+)?) +([-+]?(?:\d+\.?\d*|\.\d+)(?:[eE][-+]?\d+)?)"#,
+        r#"|(STRING)"#,
+        r#"|(ENUM) *((?:"[^"]*"(?: *, *"[^"]*")*)?))\s*;$"#
+    );
+
+    fn from(cap: &Captures) -> Result<Self, DbcError> {
+        let object = match cap.get(1).map(|m| m.as_str()) {
+            Some("BU_") => AttributeObject::Node,
+            Some("BO_") => AttributeObject::Message,
+            Some("SG_") => AttributeObject::Signal,
+            Some("EV_") => AttributeObject::EnvVar,
+            _ => AttributeObject::Global
+        };
+
+        let value_type = if let Some(kind) = cap.get(3) {
+            let min = parse_number(&cap[4], "attribute minimum")?;
+            let max = parse_number(&cap[5], "attribute maximum")?;
+            if kind.as_str() == "INT" {
+                AttributeType::Int { min, max }
+            }
+            else {
+                AttributeType::Hex { min, max }
+            }
+        }
+        else if cap.get(6).is_some() {
+            AttributeType::Float {
+                min: parse_number(&cap[7], "attribute minimum")?,
+                max: parse_number(&cap[8], "attribute maximum")?
+            }
+        }
+        else if cap.get(9).is_some() {
+            AttributeType::String
+        }
+        else {
+            AttributeType::Enum(QUOTED.captures_iter(&cap[11])
+                .map(|value| value[1].to_string())
+                .collect())
+        };
+
+        Ok(AttributeDefinition {
+            name: cap[2].to_string(),
+            object,
+            value_type,
+            default: None
+        })
+    }
+}
+
+impl DbcType for AttributeAssignment {
+    const TAG: &'static str = "BA_ ";
+    const REGEX: &'static str = concat!(
+        r#"^BA_ +"(\w+)" +(?:BU_ +(\w+) +|BO_ +(\d+) +|SG_ +(\d+) +(\w+) +|EV_ +(\w+) +)?"#,
+        r#"("[^"]*"|-?0[xX][0-9a-fA-F]+|[-+]?(?:\d+\.?\d*|\.\d+)(?:[eE][-+]?\d+)?)\s*;$"#
+    );
+
+    fn from(cap: &Captures) -> Result<Self, DbcError> {
+        let target = if let Some(node) = cap.get(2) {
+            AttributeTarget::Node(node.as_str().to_string())
+        }
+        else if let Some(id) = cap.get(3) {
+            AttributeTarget::Message(parse_number(id.as_str(), "message id")?)
+        }
+        else if let Some(id) = cap.get(4) {
+            AttributeTarget::Signal(parse_number(id.as_str(), "message id")?, cap[5].to_string())
+        }
+        else if cap.get(6).is_some() {
+            AttributeTarget::EnvVar
+        }
+        else {
+            AttributeTarget::Global
+        };
+
+        Ok(AttributeAssignment {
+            name: cap[1].to_string(),
+            target,
+            value: cap[7].to_string()
+        })
+    }
+}
+
+impl AttributeAssignment {
+    fn value(&self, definition: Option<&AttributeDefinition>) -> AttributeValue {
+        attribute_value(&self.value, definition)
+    }
+}
+
+impl DbcType for AttributeDefault {
+    const TAG: &'static str = "BA_DEF_DEF_ ";
+    const REGEX: &'static str = concat!(
+        r#"^BA_DEF_DEF_ +"(\w+)" +"#,
+        r#"("[^"]*"|-?0[xX][0-9a-fA-F]+|[-+]?(?:\d+\.?\d*|\.\d+)(?:[eE][-+]?\d+)?)\s*;$"#
+    );
+
+    fn from(cap: &Captures) -> Result<Self, DbcError> {
+        Ok(AttributeDefault {
+            name: cap[1].to_string(),
+            value: cap[2].to_string()
+        })
+    }
+}
+
+fn attribute_value(text: &str, definition: Option<&AttributeDefinition>) -> AttributeValue {
+    if let Some(text) = text.strip_prefix('"') {
+        return AttributeValue::String(text.trim_end_matches('"').to_string());
+    }
+
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        // Out of range hex values are kept as text rather than dropped
+        return match i64::from_str_radix(hex, 16) {
+            Ok(value) if negative => AttributeValue::Int(-value),
+            Ok(value) => AttributeValue::Int(value),
+            Err(_) => AttributeValue::String(text.to_string()),
+        };
+    }
+
+    let is_float = definition.is_some_and(|definition| {
+        matches!(definition.value_type, AttributeType::Float { .. })
+    });
+    match text.parse::<i64>() {
+        Ok(value) if !is_float => AttributeValue::Int(value),
+        // The regex only lets valid numbers through
+        _ => AttributeValue::Float(text.parse().unwrap_or(0.0)),
+    }
+}
+
+impl DbcType for EnvVar {
+    const TAG: &'static str = "EV_ ";
+    const REGEX: &'static str = concat!(
+        r#"^EV_ +(\w+) *: *([012]) +\[([-+]?(?:\d+\.?\d*|\.\d+)(?:[eE][-+]?\d+)?)\|([-+]?(?:\d+\.?\d*|\.\d+)(?:[eE][-+]?\d+)?)\] +"((?:[^"\\]|\\.)*)" +"#,
+        r"([-+]?(?:\d+\.?\d*|\.\d+)(?:[eE][-+]?\d+)?) +(\d+) +DUMMY_NODE_VECTOR(?:800)?([0-3]) +(\w+(?:\s*,\s*\w+)*)\s*;$"
+    );
+
+    fn from(cap: &Captures) -> Result<Self, DbcError> {
+        Ok(EnvVar {
+            name: cap[1].to_string(),
+            var_type: match &cap[2] {
+                "0" => EnvVarType::Integer,
+                "1" => EnvVarType::Float,
+                _ => EnvVarType::String,
+            },
+            minimum: parse_number(&cap[3], "minimum value")?,
+            maximum: parse_number(&cap[4], "maximum value")?,
+            unit: unescape(&cap[5]),
+            initial_value: parse_number(&cap[6], "initial value")?,
+            id: parse_number(&cap[7], "environment variable id")?,
+            access_type: match &cap[8] {
+                "0" => EnvVarAccess::Unrestricted,
+                "1" => EnvVarAccess::Read,
+                "2" => EnvVarAccess::Write,
+                _ => EnvVarAccess::ReadWrite,
+            },
+            access_nodes: cap[9].split(',')
+                .map(|node| node.trim())
+                .filter(|node| *node != "Vector__XXX")
+                .map(|node| node.to_string())
+                .collect()
+        })
+    }
+}
+
+impl DbcType for MessageSignalGroup {
+    const TAG: &'static str = "SIG_GROUP_ ";
+    const REGEX: &'static str = r"^SIG_GROUP_ +(\d+) +(\w+) +(\d+) *:((?:\s+\w+)*)\s*;$";
+
+    fn from(cap: &Captures) -> Result<Self, DbcError> {
+        Ok(MessageSignalGroup {
+            message_id: parse_number(&cap[1], "message id")?,
+            group: SignalGroup {
+                name: cap[2].to_string(),
+                repetitions: parse_number(&cap[3], "repetitions")?,
+                signals: cap[4].split_whitespace().map(|signal| signal.to_string()).collect()
+            }
+        })
+    }
+}
+
+impl DbcType for SignalMultiplexValues {
+    const TAG: &'static str = "SG_MUL_VAL_ ";
+    const REGEX: &'static str = r"^SG_MUL_VAL_ +(\d+) +(\w+) +(\w+) +(\d+ *- *\d+(?: *, *\d+ *- *\d+)*)\s*;$";
+
+    fn from(cap: &Captures) -> Result<Self, DbcError> {
+        let mut ranges = Vec::new();
+        for range in cap[4].split(',') {
+            let (min, max) = range.split_once('-').ok_or(DbcError::InvalidContent)?;
+            ranges.push((parse_number(min.trim(), "multiplexor range")?, parse_number(max.trim(), "multiplexor range")?));
+        }
+
+        Ok(SignalMultiplexValues {
+            message_id: parse_number(&cap[1], "message id")?,
+            signal_name: cap[2].to_string(),
+            multiplex: ExtendedMultiplex {
+                multiplexor: cap[3].to_string(),
+                ranges
+            }
+        })
+    }
+}
+
+impl DbcType for ValueDescriptions {
+    const TAG: &'static str = "VAL_ ";
+    const REGEX: &'static str = r#"^VAL_ (\d+) (\w+)(?:\s+([A-Za-z_]\w*)|((?:\s+-?\d+\s+"(?:[^"\\]|\\.)*")*))\s*;$"#;
+
+    fn from(cap: &Captures) -> Result<Self, DbcError> {
+        Ok(ValueDescriptions {
+            message_id: parse_number(&cap[1], "message id")?,
+            signal_name: cap[2].to_string(),
+            table: cap.get(3).map(|table| table.as_str().to_string()),
+            descriptions: value_descriptions(cap.get(4).map_or("", |pairs| pairs.as_str()))?
+        })
+    }
+}
+
+impl DbcType for SignalValueType {
+    const TAG: &'static str = "SIG_VALTYPE_ ";
+    const REGEX: &'static str = r"^SIG_VALTYPE_ +(\d+) +(\w+) *:? *([012]) *;$";
+
+    fn from(cap: &Captures) -> Result<Self, DbcError> {
+        Ok(SignalValueType {
+            message_id: parse_number(&cap[1], "message id")?,
+            signal_name: cap[2].to_string(),
+            value_type: match &cap[3] {
+                "1" => ValueType::Float,
+                "2" => ValueType::Double,
+                _ => ValueType::Integer
+            }
+        })
+    }
+}
+
+impl DbcType for ValueTable {
+    const TAG: &'static str = "VAL_TABLE_ ";
+    const REGEX: &'static str = r#"^VAL_TABLE_ +(\w+)((?:\s+-?\d+\s+"(?:[^"\\]|\\.)*")*)\s*;$"#;
+
+    fn from(cap: &Captures) -> Result<Self, DbcError> {
+        Ok(ValueTable {
+            name: cap[1].to_string(),
+            descriptions: value_descriptions(&cap[2])?
+        })
+    }
+}
+
+fn value_descriptions(pairs: &str) -> Result<HashMap<i64, String>, DbcError> {
+    let mut descriptions = HashMap::new();
+    for pair in VALUE_DESCRIPTION.captures_iter(pairs) {
+        descriptions.insert(parse_number(&pair[1], "value description")?, unescape(&pair[2]));
+    }
+    Ok(descriptions)
+}
+
+// Consecutive parts of the message and signal syntax, used to point out
+// the first field that does not match when a line is invalid
+const MESSAGE_PARTS: &[(&str, &str)] = &[
+    (r"^BO_ \d+", "Invalid message start, expected a numeric message id"),
+    (r" [A-Za-z_][A-Za-z0-9_]*", "Invalid message start, expected the message name"),
+    (r" *:", "Invalid message start, expected `:` after the message name"),
+    (r" \d+", "Invalid message start, expected the message size"),
+    (r" [A-Za-z_][A-Za-z0-9_]*", "Invalid message start, expected the transmitter"),
+];
+
+const SIGNAL_PARTS: &[(&str, &str)] = &[
+    (r"^SG_\s+[A-Za-z_][A-Za-z0-9_]*", "Invalid signal, expected the signal name"),
+    (r"(?:\s+(?:M|m\d+))?\s*:", "Invalid signal, expected `:` after the signal name"),
+    (r"\s*\d+", "Invalid signal, expected the start bit"),
+    (r"\|\d+", "Invalid signal, expected `|` and the size after the start bit"),
+    (r"@", "Invalid signal, expected `@` byte-order marker after the size"),
+    (r"[01]", "Invalid signal, expected byte-order digit `0` or `1` after `@`"),
+    (r"\s*[\+|\-]", "Invalid signal, expected the value type sign after the byte order"),
+    (r"\s*\([-+]?(?:\d+\.?\d*|\.\d+)(?:[eE][-+]?\d+)?,", "Invalid signal, expected the factor"),
+    (r"[-+]?(?:\d+\.?\d*|\.\d+)(?:[eE][-+]?\d+)?\)", "Invalid signal, expected the offset"),
+    (r"\s*\[[-+]?(?:\d+\.?\d*|\.\d+)(?:[eE][-+]?\d+)?\|", "Invalid signal, expected the minimum value"),
+    (r"[-+]?(?:\d+\.?\d*|\.\d+)(?:[eE][-+]?\d+)?\]", "Invalid signal, expected the maximum value"),
+    (r#"\s*"(?:[^"\\]|\\.)*""#, "Invalid signal, expected the quoted unit"),
+    (r"\s+.*", "Invalid signal, expected the receivers"),
+];
+
+fn field_probes(parts: &[(&str, &'static str)]) -> Vec<(Regex, &'static str)> {
+    let mut pattern = String::new();
+    parts.iter()
+        .map(|(part, reason)| {
+            pattern.push_str(part);
+            (Regex::new(&pattern), *reason)
+        })
+        .collect()
+}
+
+fn invalid_field(content: &str, probes: &[(Regex, &'static str)]) -> &'static str {
+    let content = content.trim();
+    probes.iter()
+        .find(|(probe, _)| !probe.is_match(content))
+        .or_else(|| probes.last())
+        .map(|(_, reason)| *reason)
+        .unwrap_or("Invalid content")
+}
+
+// Thin wrapper giving regex-automata's meta::Regex (the engine of the regex
+// crate, which needs std) the small part of the regex API used here
+struct Regex(meta::Regex);
+
+struct Captures<'h> {
+    haystack: &'h str,
+    groups: captures::Captures
+}
+
+struct Match<'h>(&'h str);
+
+impl Regex {
+    // The patterns are constants, so one that does not build is a bug
+    fn new(pattern: &str) -> Regex {
+        Regex(meta::Regex::new(pattern).unwrap())
+    }
+
+    fn is_match(&self, haystack: &str) -> bool {
+        self.0.is_match(haystack)
+    }
+
+    fn captures<'h>(&self, haystack: &'h str) -> Option<Captures<'h>> {
+        let mut groups = self.0.create_captures();
+        self.0.captures(haystack, &mut groups);
+        Some(Captures { haystack, groups }).filter(|captures| captures.groups.is_match())
+    }
+
+    fn captures_iter<'r, 'h: 'r>(&'r self, haystack: &'h str) -> impl Iterator<Item = Captures<'h>> + 'r {
+        self.0.captures_iter(haystack).map(move |groups| Captures { haystack, groups })
+    }
+}
+
+impl<'h> Captures<'h> {
+    fn get(&self, index: usize) -> Option<Match<'h>> {
+        self.groups.get_group(index).map(|span| Match(&self.haystack[span.start..span.end]))
+    }
+}
+
+// Like the regex crate, a group that did not take part in the match panics
+impl core::ops::Index<usize> for Captures<'_> {
+    type Output = str;
+
+    fn index(&self, index: usize) -> &str {
+        self.get(index).map(|group| group.as_str()).unwrap()
+    }
+}
+
+impl<'h> Match<'h> {
+    fn as_str(&self) -> &'h str {
+        self.0
+    }
+}
+
+// Built on first use, like lazy_static, behind OnceLock with std and
+// spin's Once (a spinlock) without it
+struct Lazy<T> {
+    #[cfg(feature = "std")]
+    cell: std::sync::OnceLock<T>,
+    #[cfg(not(feature = "std"))]
+    cell: spin::Once<T>,
+    init: fn() -> T
+}
+
+impl<T> Lazy<T> {
+    const fn new(init: fn() -> T) -> Self {
+        Lazy {
+            #[cfg(feature = "std")]
+            cell: std::sync::OnceLock::new(),
+            #[cfg(not(feature = "std"))]
+            cell: spin::Once::new(),
+            init
+        }
+    }
+}
+
+impl<T> core::ops::Deref for Lazy<T> {
+    type Target = T;
+
+    #[cfg(feature = "std")]
+    fn deref(&self) -> &T {
+        self.cell.get_or_init(self.init)
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn deref(&self) -> &T {
+        self.cell.call_once(self.init)
+    }
+}
+
+static MESSAGE_FIELDS: Lazy<Vec<(Regex, &'static str)>> = Lazy::new(|| field_probes(MESSAGE_PARTS));
+static SIGNAL_FIELDS: Lazy<Vec<(Regex, &'static str)>> = Lazy::new(|| field_probes(SIGNAL_PARTS));
+static HASHMAP: Lazy<HashMap<&'static str, Regex>> = Lazy::new(|| {
+    let mut m = HashMap::new();
+    m.insert(Node::REGEX, Regex::new(Node::REGEX));
+    m.insert(Message::REGEX, Regex::new(Message::REGEX));
+    m.insert(Signal::REGEX, Regex::new(Signal::REGEX));
+    m.insert(SignalMultiplexValues::REGEX, Regex::new(SignalMultiplexValues::REGEX));
+    m.insert(ValueDescriptions::REGEX, Regex::new(ValueDescriptions::REGEX));
+    m.insert(ValueTable::REGEX, Regex::new(ValueTable::REGEX));
+    m.insert(SignalValueType::REGEX, Regex::new(SignalValueType::REGEX));
+    m.insert(Comment::REGEX, Regex::new(Comment::REGEX));
+    m.insert(Version::REGEX, Regex::new(Version::REGEX));
+    m.insert(BitTiming::REGEX, Regex::new(BitTiming::REGEX));
+    m.insert(EnvVar::REGEX, Regex::new(EnvVar::REGEX));
+    m.insert(MessageSignalGroup::REGEX, Regex::new(MessageSignalGroup::REGEX));
+    m.insert(MessageTransmitters::REGEX, Regex::new(MessageTransmitters::REGEX));
+    m.insert(AttributeDefinition::REGEX, Regex::new(AttributeDefinition::REGEX));
+    m.insert(AttributeDefault::REGEX, Regex::new(AttributeDefault::REGEX));
+    m.insert(AttributeAssignment::REGEX, Regex::new(AttributeAssignment::REGEX));
+    m
+});
+static VALUE_DESCRIPTION: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(-?\d+)\s+"((?:[^"\\]|\\.)*)""#));
+static QUOTED: Lazy<Regex> = Lazy::new(|| Regex::new(r#""([^"]*)""#));
+
+impl<I: Iterator> LogicalLines<I> {
+    fn new(lines: I) -> Self {
+        LogicalLines { lines: lines.enumerate() }
+    }
+}
+
+impl<E, I: Iterator<Item = Result<String, E>>> Iterator for LogicalLines<I> {
+    type Item = Result<(usize, String), E>;
+
+    // Quoted strings (e.g. comments) may span several lines, so those are
+    // joined back into a single logical line. Only the sections with free
+    // text are joined, a stray quote anywhere else stays on its own line
+    fn next(&mut self) -> Option<Self::Item> {
+        let (i, first) = self.lines.next()?;
+        let mut line = match first {
+            Ok(line) => strip_carriage_return(line),
+            Err(e) => return Some(Err(e)),
+        };
+        // Some editors start the file with a UTF-8 byte order mark
+        if i == 0 && line.starts_with('\u{feff}') {
+            line.remove(0);
+        }
+        let is_multi_line = first_keyword(&line).is_some_and(|keyword| MULTI_LINE_KEYWORDS.contains(&keyword));
+        while is_multi_line && has_open_quote(&line) {
+            match self.lines.next() {
+                Some((_, Ok(next))) => {
+                    line.push('\n');
+                    line.push_str(&strip_carriage_return(next));
+                },
+                Some((_, Err(e))) => return Some(Err(e)),
+                None => break,
+            }
+        }
+
+        Some(Ok((i, line)))
+    }
+}
+
+// Windows line endings leave a carriage return behind whenever the
+// line was not split on "\r\n" (e.g. stray "\r\r\n" endings)
+fn strip_carriage_return(mut line: String) -> String {
+    let len = line.trim_end_matches('\r').len();
+    line.truncate(len);
+    line
+}
+
+// Sections whose quoted text may span several lines
+const MULTI_LINE_KEYWORDS: &[&str] = &["CM_", "BA_", "BA_DEF_", "VAL_", "EV_"];
+
+// Header sections without content of interest
+const IGNORED_KEYWORDS: &[&str] = &["NS_"];
+
+// The NS_ block lists one indented keyword per line and ends with
+// a blank line, BS_ or any unindented line
+fn is_new_symbol(line: &str) -> bool {
+    line.starts_with(char::is_whitespace) && !line.trim().is_empty()
+}
+
+// Sections that are recognized but not parsed (yet)
+const UNSUPPORTED_KEYWORDS: &[&str] = &[
+    "NS_DESC_", "EV_DATA_", "ENVVAR_DATA_",
+    "SIGTYPE_VALTYPE_",
+    "CAT_DEF_", "CAT_", "FILTER", "SGTYPE_", "SGTYPE_VAL_", "BA_DEF_SGTYPE_", "BA_SGTYPE_",
+    "SIG_TYPE_REF_", "BA_DEF_REL_", "BA_REL_", "BA_DEF_DEF_REL_", "BU_SG_REL_", "BU_EV_REL_",
+    "BU_BO_REL_"
+];
+
+// Numbers matched by a regex may still be out of range for their type. The
+// line is not known here and is filled in by the parse loop
+fn parse_number<T: FromStr>(content: &str, field: &'static str) -> Result<T, DbcError> {
+    content.parse().map_err(|_| DbcError::NumberParse { line: 0, field })
+}
+
+// Attaches the line to an error from parse_type
+fn line_error(error: DbcError, i: usize, line: &str, reason: &'static str) -> DbcError {
+    match error {
+        DbcError::NumberParse { field, .. } => DbcError::NumberParse { line: i+1, field },
+        _ => DbcError::InvalidLine { line: i+1, content: line.to_string(), reason },
+    }
+}
+
+fn first_keyword(content: &str) -> Option<&str> {
+    content.split_whitespace().next().map(|token| token.trim_end_matches(':'))
+}
+
+fn csv_field(content: &str) -> String {
+    if content.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", content.replace('"', "\"\""))
+    }
+    else {
+        content.to_string()
+    }
+}
+
+// f64::round and f64::powi need std, these are the same for the values used here
+
+// Rounds half away from zero
+fn round(value: f64) -> f64 {
+    // From 2^52 on every f64 is a whole number
+    const WHOLE: f64 = 4_503_599_627_370_496.0;
+    let magnitude = if value < 0.0 { -value } else { value };
+    if magnitude >= WHOLE || value.is_nan() {
+        return value;
+    }
+    let truncated = magnitude as u64 as f64;
+    let rounded = if magnitude - truncated >= 0.5 { truncated + 1.0 } else { truncated };
+    if value < 0.0 { -rounded } else { rounded }
+}
+
+// Powers of two down to 2^-1022, anything past 2^1023 is infinite
+fn pow2(exponent: i32) -> f64 {
+    match exponent {
+        1024.. => f64::INFINITY,
+        -1022..=1023 => f64::from_bits(((exponent + 1023) as u64) << 52),
+        _ => 0.0,
+    }
+}
+
+fn escape(content: &str) -> String {
+    content.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn unescape(content: &str) -> String {
+    let mut unescaped = String::with_capacity(content.len());
+    let mut chars = content.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            _ => unescaped.push(c),
+        }
+    }
+
+    unescaped
+}
+
+fn has_open_quote(content: &str) -> bool {
+    let mut open = false;
+    let mut escaped = false;
+    for c in content.chars() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => open = !open,
+            _ => escaped = false,
+        }
+    }
+
+    open
+}
+
+fn parse_type<T: DbcType>(content: &str) -> Result<T, DbcError> {
+    let content = content.trim();
+    let re = HASHMAP.get(T::REGEX).unwrap();
+
+    if !re.is_match(content) {
+        // Comparing whole keywords keeps e.g. SG_MUL_VAL_ from being taken
+        // as SG_, and a bare keyword (as listed under NS_) is not an error
+        let tag = T::TAG.trim_end();
+        if first_keyword(content) != Some(tag) || content == tag {
+            return Err(DbcError::WrongType);
+        }
+        else {
+            return Err(DbcError::InvalidContent);
+        }
+    }
+
+    let cap = re.captures(content).unwrap();
+
+    T::from(&cap)
+}
+
+fn parse_type_vec<T: DbcType>(content: &str) -> Result<Vec<T>, DbcError> {
+    let content = content.trim();
+    let re = HASHMAP.get(T::REGEX).unwrap();
+
+    // The tag has to be a whole keyword, so BU_SG_REL_ and the
+    // like are not taken as a node list
+    let rest = match content.strip_prefix(T::TAG) {
+        Some(rest) if rest.is_empty() || rest.starts_with(':') || rest.starts_with(char::is_whitespace) => rest,
+        _ => return Err(DbcError::WrongType),
+    };
+    let rest = rest.trim_start();
+    let rest = rest.strip_prefix(':').unwrap_or(rest);
+
+    rest.split_whitespace()
+        .map(|token| match re.captures(token) {
+            Some(cap) => T::from(&cap),
+            None => Err(DbcError::InvalidContent),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
+mod tests {
+    use super::*;
+    #[cfg(not(feature = "std"))]
+    use std::boxed::Box;
+
+    fn parse_signal(contents: &str) -> Result<Signal, DbcError> {
+        parse_type::<Signal>(contents)
+    }
+
+    fn parse_message(contents: &str) -> Result<Message, DbcError> {
+        parse_type::<Message>(contents)
+    }
+
+    fn parse_nodes(contents: &str) -> Result<Vec<Node>, DbcError> {
+        parse_type_vec::<Node>(contents)
+    }
+
+    struct Setup<'a>{
+        test_messages: &'a str
+    }
+
+    impl Setup<'_> {
+        fn new() -> Self {
+            let test_messages = "
+BU_: TCU VEHICLE
+
+BO_ 2566117891 MsgDummy1: 8 Vector__XXX
+ SG_ dummy1sg1 : 34|2@1+ (1,0) [0|3] \"kkk\" Vector__XXX
+ SG_ dummy1sg2 : 18|16@1- (1,0) [0|65535] \"\" Vector__XXX
+ SG_ dummy1sg3 : 2|16@1+ (1,0) [0|65535] \"\" Vector__XXX
+ SG_ dummy1sg4 : 0|2@1+ (1,0) [0|3] \"\" Vector__XXX
+
+BO_ 2565921559 MsgDummy2: 8 Vector__XXX
+ SG_ gps_longitude : 39|32@0- (1E-007,0) [-214.7483648|214.7483647] \"deg\" Vector__XXX
+ SG_ gps_latitude : 7|32@0- (1E-007,0) [-214.7483648|214.7483647] \"deg\" Vector__XXX
+
+BO_ 2565986819 MsgDummy3: 8 TCU
+ SG_ dummy3sg1 : 16|16@1+ (0.125,0) [0|8191.875] \"\" Vector__XXX
+";
+            Self { test_messages }
+        }
+    }
+
+    #[test]
+    fn valid_message_start() {
+        let content = "BO_ 2566117891 MsgDummy1: 8 Vector__XXX";
+        assert_eq!(parse_message(content).is_ok(), true);
+    }
+
+    #[test]
+    fn invalid_message_start() {
+        let content = "BO_ 2566117891 MsgDummy1: Vector__XXX";
+        assert_eq!(parse_message(content).err().unwrap(), DbcError::InvalidContent);
+    }
+
+    #[test]
+    fn not_message() {
+        let content = "SG_ dummy1sg1 : 34|2@1+ (1,0) [0|3] \"kkk\" Vector__XXX";
+        assert_eq!(parse_message(content).err().unwrap(), DbcError::WrongType);
+    }
+
+    #[test]
+    fn message_transmitter() {
+        let setup = Setup::new();
+        let messages = parse(setup.test_messages).unwrap().messages;
+        assert_eq!(messages[2].transmitters, vec!["TCU"]);
+        assert!(messages[0].transmitters.is_empty());
+    }
+
+    #[test]
+    fn additional_transmitters() {
+        let setup = Setup::new();
+        let contents = format!("{}
+BO_TX_BU_ 2565986819 : TCU,VEHICLE;
+BO_TX_BU_ 2566117891 : TCU, VEHICLE;
+BO_TX_BU_ 1234 : TCU;
+", setup.test_messages);
+        let dbc = parse(&contents).unwrap();
+        assert_eq!(dbc.messages[2].transmitters, vec!["TCU", "VEHICLE"]);
+        assert_eq!(dbc.messages[0].transmitters, vec!["TCU", "VEHICLE"]);
+        assert_eq!(parse(&dbc.to_dbc_string()).unwrap(), dbc);
+    }
+
+    #[test]
+    fn valid_signal() {
+        let content = "SG_ dummy1sg1 : 34|2@1+ (1,0) [0|3] \"kkk\" Vector__XXX";
+        assert_eq!(parse_signal(content).is_ok(), true);
+    }
+
+    #[test]
+    fn invalid_signal() {
+        let content = "SG_ dummy1sg1 : 34|21+ (1,0) [0|3] \"kkk\" Vector__XXX";
+        assert_eq!(parse_signal(content).err().unwrap(), DbcError::InvalidContent);
+    }
+
+    #[test]
+    fn not_signal() {
+        let content = "BO_ 2566117891 MsgDummy1: 8 Vector__XXX";
+        assert_eq!(parse_signal(content).err().unwrap(), DbcError::WrongType);
+    }
+
+    #[test]
+    fn num_signals() {
+        let setup = Setup::new();
+        let messages = parse(setup.test_messages).unwrap().messages;
+        assert_eq!(messages[0].signals.len(), 4);
+        assert_eq!(messages[1].signals.len(), 2);
+        assert_eq!(messages[2].signals.len(), 1);
+    }
+
+    #[test]
+    fn signal_values() {
+        let setup = Setup::new();
+        let messages = parse(setup.test_messages).unwrap().messages;
+        assert_eq!(messages[1].signals[0].name, "gps_longitude");
+        assert_eq!(messages[1].signals[0].start_bit, 39);
+        assert_eq!(messages[1].signals[0].size, 32);
+        assert_eq!(messages[1].signals[0].value_min, -214.7483648);
+        assert_eq!(messages[1].signals[0].value_max, 214.7483647);
+        assert_eq!(messages[1].signals[0].unit, "deg");
+        assert_eq!(messages[1].signals[0].is_little_endian, false);
+        assert_eq!(messages[1].signals[0].is_signed, true);
+    }
+
+    #[test]
+    fn signal_receivers() {
+        let content = "SG_ dummy1sg1 : 34|2@1+ (1,0) [0|3] \"kkk\" TCU,VEHICLE";
+        assert_eq!(parse_signal(content).unwrap().receivers, vec!["TCU", "VEHICLE"]);
+
+        let content = "SG_ dummy1sg1 : 34|2@1+ (1,0) [0|3] \"kkk\" Vector__XXX";
+        assert!(parse_signal(content).unwrap().receivers.is_empty());
+    }
+
+    #[test]
+    fn signal_numeric_values() {
+        let setup = Setup::new();
+        let messages = parse(setup.test_messages).unwrap().messages;
+        assert_eq!(messages[1].signals[0].factor, 1e-7);
+        assert_eq!(messages[1].signals[0].offset, 0.0);
+        assert_eq!(messages[1].signals[0].value_min, -214.7483648);
+        assert_eq!(messages[2].signals[0].factor, 0.125);
+        assert_eq!(messages[2].signals[0].value_max, 8191.875);
+    }
+
+    #[test]
+    fn nodes() {
+        let content = "BU_: TCU VEHICLE";
+        let nodes = parse_nodes(content).unwrap();
+        assert_eq!(nodes[0].name, "TCU");
+        assert_eq!(nodes[1].name, "VEHICLE");
+    }
+
+    #[test]
+    fn all_nodes() {
+        let setup = Setup::new();
+        let nodes = parse(setup.test_messages).unwrap().nodes;
+        assert_eq!(nodes[0].name, "TCU");
+        assert_eq!(nodes[1].name, "VEHICLE");
+    }
+
+    #[test]
+    fn invalid_line_error() {
+        let content = "
+BO_ 2566117891 MsgDummy1: 8 Vector__XXX
+ SG_ dummy1sg1 : 34|21+ (1,0) [0|3] \"kkk\" Vector__XXX
+";
+        let err = parse(content).err().unwrap();
+        assert_eq!(err, DbcError::InvalidLine {
+            line: 3,
+            content: " SG_ dummy1sg1 : 34|21+ (1,0) [0|3] \"kkk\" Vector__XXX".to_string(),
+            reason: "Invalid signal, expected `@` byte-order marker after the size"
+        });
+    }
+
+    #[test]
+    fn invalid_line_display() {
+        let content = "BO_ 2566117891 MsgDummy1: Vector__XXX";
+        let err = parse(content).err().unwrap();
+        assert_eq!(err.to_string(), "Error when parsing line 1: BO_ 2566117891 MsgDummy1: Vector__XXX. Invalid message start, expected the message size.");
+    }
+
+    #[test]
+    fn message_by_id() {
+        let setup = Setup::new();
+        let dbc = parse(setup.test_messages).unwrap();
+        assert_eq!(dbc.message_by_id(2565921559).unwrap().name, "MsgDummy2");
+        assert!(dbc.message_by_id(1234).is_none());
+    }
+
+    #[test]
+    fn message_by_name() {
+        let setup = Setup::new();
+        let dbc = parse(setup.test_messages).unwrap();
+        assert_eq!(dbc.message_by_name("MsgDummy3").unwrap().id, 2565986819);
+        assert!(dbc.message_by_name("MsgDummy4").is_none());
+    }
+
+    #[test]
+    fn message_by_id_after_edit() {
+        let setup = Setup::new();
+        let mut dbc = parse(setup.test_messages).unwrap();
+        dbc.messages.remove(0);
+        assert_eq!(dbc.message_by_id(2565921559).unwrap().name, "MsgDummy2");
+        assert!(dbc.message_by_id(2566117891).is_none());
+    }
+
+    #[test]
+    fn value_descriptions() {
+        let setup = Setup::new();
+        let contents = format!("{}
+VAL_ 2566117891 dummy1sg1 0 \"Off\" 1 \"On\" 2 \"Error\" ;
+", setup.test_messages);
+        let messages = parse(&contents).unwrap().messages;
+        let descriptions = &messages[0].signals[0].value_descriptions;
+        assert_eq!(descriptions.len(), 3);
+        assert_eq!(descriptions[&0], "Off");
+        assert_eq!(descriptions[&1], "On");
+        assert_eq!(descriptions[&2], "Error");
+        assert!(messages[0].signals[1].value_descriptions.is_empty());
+    }
+
+    #[test]
+    fn value_descriptions_unknown_target() {
+        let setup = Setup::new();
+        let contents = format!("{}
+VAL_ 1234 dummy1sg1 0 \"Off\" 1 \"On\" ;
+VAL_ 2566117891 unknown 0 \"Off\" 1 \"On\" ;
+", setup.test_messages);
+        let messages = parse(&contents).unwrap().messages;
+        assert!(messages.iter().all(|message| message.signals.iter()
+            .all(|signal| signal.value_descriptions.is_empty())));
+    }
+
+    #[test]
+    fn invalid_value_descriptions() {
+        let content = "VAL_ 2566117891 dummy1sg1 0 \"Off\" 1 ;";
+        assert_eq!(parse_type::<ValueDescriptions>(content).err().unwrap(), DbcError::InvalidContent);
+    }
+
+    #[test]
+    fn decode_intel_signals() {
+        let setup = Setup::new();
+        let messages = parse(setup.test_messages).unwrap().messages;
+        let data = [0xd3, 0x48, 0xf8, 0xff, 0x0b, 0x00, 0x00, 0x00];
+        let values = messages[0].decode(&data);
+        assert_eq!(values["dummy1sg1"], 2.0);
+        assert_eq!(values["dummy1sg2"], -2.0);
+        assert_eq!(values["dummy1sg3"], 4660.0);
+        assert_eq!(values["dummy1sg4"], 3.0);
+    }
+
+    #[test]
+    fn decode_motorola_signals() {
+        let setup = Setup::new();
+        let messages = parse(setup.test_messages).unwrap().messages;
+        let data = [0x1c, 0xaf, 0x0e, 0x87, 0xf9, 0x25, 0x3d, 0x40];
+        let values = messages[1].decode(&data);
+        assert!((values["gps_latitude"] - 48.1234567).abs() < 1e-9);
+        assert!((values["gps_longitude"] - -11.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn decode_scaled_signal() {
+        let setup = Setup::new();
+        let messages = parse(setup.test_messages).unwrap().messages;
+        let data = [0x00, 0x00, 0x0a, 0x00, 0x00, 0x00, 0x00, 0x00];
+        assert_eq!(messages[2].signals[0].decode(&data), 1.25);
+    }
+
+    #[test]
+    fn encode_round_trip() {
+        let setup = Setup::new();
+        let messages = parse(setup.test_messages).unwrap().messages;
+        let mut values = HashMap::new();
+        values.insert("gps_latitude".to_string(), 48.1234567);
+        values.insert("gps_longitude".to_string(), -11.5);
+        let data = messages[1].encode(&values);
+        assert_eq!(data, [0x1c, 0xaf, 0x0e, 0x87, 0xf9, 0x25, 0x3d, 0x40]);
+
+        let decoded = messages[1].decode(&data);
+        assert!((decoded["gps_latitude"] - 48.1234567).abs() < messages[1].signals[1].factor);
+        assert!((decoded["gps_longitude"] - -11.5).abs() < messages[1].signals[0].factor);
+    }
+
+    #[test]
+    fn encode_untouched_bits() {
+        let setup = Setup::new();
+        let messages = parse(setup.test_messages).unwrap().messages;
+        let mut values = HashMap::new();
+        values.insert("dummy1sg1".to_string(), 2.0);
+        values.insert("dummy1sg2".to_string(), -2.0);
+        let data = messages[0].encode(&values);
+        assert_eq!(data, [0x00, 0x00, 0xf8, 0xff, 0x0b, 0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn encode_clamps_to_bit_width() {
+        let setup = Setup::new();
+        let messages = parse(setup.test_messages).unwrap().messages;
+        let mut data = [0u8; 8];
+        messages[0].signals[0].encode(7.0, &mut data);
+        assert_eq!(data, [0x00, 0x00, 0x00, 0x00, 0x0c, 0x00, 0x00, 0x00]);
+        messages[0].signals[1].encode(-40000.0, &mut data);
+        assert_eq!(messages[0].signals[1].decode(&data), -32768.0);
+        assert_eq!(messages[0].signals[0].decode(&data), 3.0);
+    }
+
+    #[test]
+    fn bit_positions() {
+        let setup = Setup::new();
+        let messages = parse(setup.test_messages).unwrap().messages;
+        assert_eq!(messages[0].signals[0].bit_positions(), vec![34, 35]);
+
+        let motorola = parse_signal("SG_ sig : 3|12@0+ (1,0) [0|0] \"\" Vector__XXX").unwrap();
+        assert_eq!(motorola.bit_positions(), vec![8, 9, 10, 11, 12, 13, 14, 15, 0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn bit_positions_same_field() {
+        let setup = Setup::new();
+        let messages = parse(setup.test_messages).unwrap().messages;
+        let motorola = &messages[1].signals[1];
+        let intel = parse_signal("SG_ sig : 0|32@1+ (1,0) [0|0] \"\" Vector__XXX").unwrap();
+
+        let mut motorola_positions = motorola.bit_positions();
+        motorola_positions.sort_unstable();
+        assert_eq!(motorola_positions, intel.bit_positions());
+        assert_ne!(motorola.bit_positions(), intel.bit_positions());
+    }
+
+    #[test]
+    fn msb_start_bit() {
+        let setup = Setup::new();
+        let messages = parse(setup.test_messages).unwrap().messages;
+        assert_eq!(messages[0].signals[0].msb_start_bit(), 36);
+        assert_eq!(messages[1].signals[0].msb_start_bit(), 32);
+        assert_eq!(messages[1].signals[1].msb_start_bit(), 0);
+
+        let motorola = parse_signal("SG_ sig : 3|12@0+ (1,0) [0|0] \"\" Vector__XXX").unwrap();
+        assert_eq!(motorola.msb_start_bit(), 4);
+    }
+
+    #[test]
+    fn comments() {
+        let setup = Setup::new();
+        let contents = format!("{}
+CM_ \"Global comment\";
+CM_ BU_ TCU \"Transmission control unit\";
+CM_ BO_ 2566117891 \"Engine status message\";
+CM_ SG_ 2566117891 dummy1sg1 \"Ignition state\";
+", setup.test_messages);
+        let messages = parse(&contents).unwrap().messages;
+        assert_eq!(messages[0].comment, Some("Engine status message".to_string()));
+        assert_eq!(messages[0].signals[0].comment, Some("Ignition state".to_string()));
+        assert_eq!(messages[0].signals[1].comment, None);
+        assert_eq!(messages[1].comment, None);
+    }
+
+    #[test]
+    fn multi_line_comment() {
+        let setup = Setup::new();
+        let contents = format!("{}
+CM_ SG_ 2565921559 gps_latitude \"Latitude of the vehicle;
+positive values are north\";
+CM_ BO_ 2565921559 \"GPS position\";
+", setup.test_messages);
+        let messages = parse(&contents).unwrap().messages;
+        assert_eq!(messages[1].signals[1].comment,
+            Some("Latitude of the vehicle;\npositive values are north".to_string()));
+        assert_eq!(messages[1].comment, Some("GPS position".to_string()));
+    }
+
+    #[test]
+    fn whole_keywords() {
+        let contents = "BO_ 1 Msg: 8 Vector__XXX
+ SG_ mux M : 0|8@1+ (1,0) [0|0] \"\" Vector__XXX
+ SG_ sig m1 : 8|8@1+ (1,0) [0|0] \"\" Vector__XXX
+SG_MUL_VAL_ 1 sig mux 1-1;
+";
+        let dbc = parse(contents).unwrap();
+        assert!(dbc.warnings.is_empty());
+        assert_eq!(dbc.messages[0].signals.len(), 2);
+        assert!(dbc.messages[0].signals[1].extended_multiplex.is_some());
+
+        assert_eq!(parse_signal("SG_MUL_VAL_ 1 sig mux 1-1;").err(), Some(DbcError::WrongType));
+        assert_eq!(parse_signal("SG_").err(), Some(DbcError::WrongType));
+        assert_eq!(parse_signal("SG_ sig").err(), Some(DbcError::InvalidContent));
+
+        let contents = "BO_ 1 Msg: 8 Vector__XXX
+ SG_ sig : 0|8@1+ (1,0) [0|0] \"\" Vector__XXX
+SG_
+";
+        let dbc = parse(contents).unwrap();
+        assert_eq!(dbc.messages[0].signals.len(), 1);
+        assert_eq!(dbc.warnings, vec![ParseWarning { line: 3, content: "SG_".to_string() }]);
+    }
+
+    #[test]
+    fn stray_quote() {
+        let contents = "XYZ_ \"stray
+BO_ 1 Msg: 8 Vector__XXX
+ SG_ sig : 0|8@1+ (1,0) [0|0] \"\" Vector__XXX
+";
+        let dbc = parse(contents).unwrap();
+        assert_eq!(dbc.warnings, vec![ParseWarning { line: 1, content: "XYZ_ \"stray".to_string() }]);
+        assert_eq!(dbc.messages[0].signals.len(), 1);
+
+        let contents = "BO_ 1 Msg: 8 Vector__XXX
+ SG_ sig : 0|8@1+ (1,0) [0|0] \"deg Vector__XXX
+ SG_ next : 8|8@1+ (1,0) [0|0] \"\" Vector__XXX
+";
+        match parse(contents).err().unwrap() {
+            DbcError::InvalidLine { line, content, .. } => {
+                assert_eq!(line, 2);
+                assert!(!content.contains('\n'));
+            },
+            err => panic!("unexpected error {:?}", err),
+        }
+    }
+
+    #[test]
+    fn comment_unknown_target() {
+        let setup = Setup::new();
+        let contents = format!("{}
+CM_ BO_ 1234 \"Unknown message\";
+CM_ SG_ 2566117891 unknown \"Unknown signal\";
+", setup.test_messages);
+        let messages = parse(&contents).unwrap().messages;
+        assert!(messages.iter().all(|message| message.comment.is_none()));
+    }
+
+    #[test]
+    fn multiplexed_signals() {
+        let content = "SG_ sig m2 : 8|8@1+ (1,0) [0|255] \"\" Vector__XXX";
+        let signal = parse_signal(content).unwrap();
+        assert_eq!(signal.multiplexer, MultiplexIndicator::MultiplexedBy(2));
+        assert_eq!(signal.start_bit, 8);
+
+        let content = "SG_ mux M : 0|8@1+ (1,0) [0|255] \"\" Vector__XXX";
+        let signal = parse_signal(content).unwrap();
+        assert_eq!(signal.multiplexer, MultiplexIndicator::Multiplexor);
+        assert_eq!(signal.name, "mux");
+
+        let setup = Setup::new();
+        let messages = parse(setup.test_messages).unwrap().messages;
+        assert_eq!(messages[0].signals[0].multiplexer, MultiplexIndicator::None);
+    }
+
+    #[test]
+    fn decode_multiplexed_message() {
+        let content = "
+BO_ 100 MuxMessage: 8 Vector__XXX
+ SG_ mux M : 0|8@1+ (1,0) [0|255] \"\" Vector__XXX
+ SG_ sig_a m1 : 8|8@1+ (1,0) [0|255] \"\" Vector__XXX
+ SG_ sig_b m2 : 8|8@1+ (1,0) [0|255] \"\" Vector__XXX
+ SG_ plain : 16|8@1+ (1,0) [0|255] \"\" Vector__XXX
+";
+        let messages = parse(content).unwrap().messages;
+        let values = messages[0].decode(&[0x02, 0x2a, 0x07, 0, 0, 0, 0, 0]);
+        assert_eq!(values.len(), 3);
+        assert_eq!(values["mux"], 2.0);
+        assert_eq!(values["sig_b"], 42.0);
+        assert_eq!(values["plain"], 7.0);
+        assert!(!values.contains_key("sig_a"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn to_json() {
+        let setup = Setup::new();
+        let json = parse(setup.test_messages).unwrap().to_json().unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let messages = value["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0]["id"], 2566117891u32);
+        assert_eq!(messages[1]["id"], 2565921559u32);
+        assert_eq!(messages[1]["signals"][0]["name"], "gps_longitude");
+        assert_eq!(messages[1]["signals"][1]["name"], "gps_latitude");
+        assert_eq!(value["nodes"][0]["name"], "TCU");
+        assert!(value.get("message_index").is_none());
+    }
+
+    #[test]
+    fn signal_display() {
+        let setup = Setup::new();
+        let messages = parse(setup.test_messages).unwrap().messages;
+        assert_eq!(messages[0].signals[1].to_string(),
+            "SG_ dummy1sg2 : 18|16@1- (1,0) [0|65535] \"\" Vector__XXX");
+        assert_eq!(messages[1].signals[0].to_string(),
+            "SG_ gps_longitude : 39|32@0- (0.0000001,0) [-214.7483648|214.7483647] \"deg\" Vector__XXX");
+
+        let content = "SG_ sig m2 : 8|8@1+ (1,0) [0|255] \"\" TCU,VEHICLE";
+        assert_eq!(parse_signal(content).unwrap().to_string(), content);
+    }
+
+    #[test]
+    fn message_display() {
+        let setup = Setup::new();
+        let messages = parse(setup.test_messages).unwrap().messages;
+        assert_eq!(messages[2].to_string(), "BO_ 2565986819 MsgDummy3: 8 TCU
+ SG_ dummy3sg1 : 16|16@1+ (0.125,0) [0|8191.875] \"\" Vector__XXX
+");
+    }
+
+    #[test]
+    fn to_dbc_string_round_trip() {
+        let setup = Setup::new();
+        let contents = format!("{}
+CM_ BO_ 2566117891 \"Engine status message\";
+CM_ SG_ 2566117891 dummy1sg1 \"Ignition state\";
+VAL_ 2566117891 dummy1sg1 0 \"Off\" 1 \"On\" 2 \"Error\" ;
+", setup.test_messages);
+        let dbc = parse(&contents).unwrap();
+        let output = dbc.to_dbc_string();
+        let reparsed = parse(&output).unwrap();
+        assert_eq!(reparsed, dbc);
+        assert_eq!(reparsed.to_dbc_string(), output);
+    }
+
+    #[test]
+    fn to_dbc_string_escapes_quotes() {
+        let setup = Setup::new();
+        let contents = format!("{}
+CM_ BU_ TCU \"The \\\"TCU\\\" node\";
+CM_ BO_ 2566117891 \"Engine \\\"status\\\" message\";
+CM_ SG_ 2566117891 dummy1sg1 \"Path C:\\\\dbc\";
+VAL_ 2566117891 dummy1sg1 0 \"\\\"Off\\\"\" 1 \"On\" ;
+", setup.test_messages);
+        let dbc = parse(&contents).unwrap();
+        let messages = &dbc.messages;
+        assert_eq!(dbc.nodes[0].comment.as_deref(), Some("The \"TCU\" node"));
+        assert_eq!(messages[0].comment.as_deref(), Some("Engine \"status\" message"));
+        assert_eq!(messages[0].signals[0].comment.as_deref(), Some("Path C:\\dbc"));
+        assert_eq!(messages[0].signals[0].value_descriptions[&0], "\"Off\"");
+
+        let output = dbc.to_dbc_string();
+        assert!(output.contains("CM_ BO_ 2566117891 \"Engine \\\"status\\\" message\";"));
+        let reparsed = parse(&output).unwrap();
+        assert_eq!(reparsed, dbc);
+        assert_eq!(reparsed.to_dbc_string(), output);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn parse_reader_matches_parse() {
+        let setup = Setup::new();
+        let dbc = parse(setup.test_messages).unwrap();
+        let streamed = parse_reader(io::Cursor::new(setup.test_messages)).unwrap();
+        assert_eq!(streamed, dbc);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn parse_reader_io_error() {
+        let invalid_utf8: &[u8] = b"BU_: TCU\n\xff\xfe\n";
+        let err = parse_reader(invalid_utf8).err().unwrap();
+        assert!(matches!(err, DbcError::IoError(_)));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn config_missing_filename() {
+        assert_eq!(Config::new(&["prog".to_string()]).err(), Some("not enough arguments"));
+        assert_eq!(Config::new(&[]).err(), Some("not enough arguments"));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn config_filename() {
+        let config = Config::new(&["prog".to_string(), "test.dbc".to_string()]).unwrap();
+        assert_eq!(config.filenames, vec!["test.dbc"]);
+        assert_eq!(config.format, OutputFormat::Debug);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn config_format_and_multiple_files() {
+        let args: Vec<String> = ["prog", "a.dbc", "--format", "csv", "b.dbc"].iter().map(|arg| arg.to_string()).collect();
+        let config = Config::new(&args).unwrap();
+        assert_eq!(config.filenames, vec!["a.dbc", "b.dbc"]);
+        assert_eq!(config.format, OutputFormat::Csv);
+
+        let args: Vec<String> = ["prog", "--format=debug", "a.dbc"].iter().map(|arg| arg.to_string()).collect();
+        assert_eq!(Config::new(&args).unwrap().format, OutputFormat::Debug);
+
+        let args: Vec<String> = ["prog", "a.dbc", "--format", "xml"].iter().map(|arg| arg.to_string()).collect();
+        assert_eq!(Config::new(&args).err(), Some("unknown output format"));
+
+        let args: Vec<String> = ["prog", "a.dbc", "--format"].iter().map(|arg| arg.to_string()).collect();
+        assert_eq!(Config::new(&args).err(), Some("missing output format"));
+
+        let args: Vec<String> = ["prog", "--format", "csv"].iter().map(|arg| arg.to_string()).collect();
+        assert_eq!(Config::new(&args).err(), Some("not enough arguments"));
+    }
+
+    #[cfg(all(feature = "std", feature = "serde"))]
+    #[test]
+    fn config_json_format() {
+        let args: Vec<String> = ["prog", "--format", "json", "a.dbc"].iter().map(|arg| arg.to_string()).collect();
+        assert_eq!(Config::new(&args).unwrap().format, OutputFormat::Json);
+    }
+
+    #[test]
+    fn parse_is_deterministic() {
+        let setup = Setup::new();
+        let first = parse(setup.test_messages).unwrap();
+        let second = parse(setup.test_messages).unwrap();
+        assert_eq!(first, second);
+
+        let mut edited = parse(setup.test_messages).unwrap();
+        edited.messages[0].signals[0].start_bit = 0;
+        assert_ne!(first, edited);
+    }
+
+    #[test]
+    fn error_display() {
+        let err = parse("VERSION \"\"\n\nBO_ 99999999999 Msg: 8 Vector__XXX\n").unwrap_err();
+        assert_eq!(err, DbcError::NumberParse { line: 3, field: "message id" });
+        assert_eq!(err.to_string(), "Error when parsing line 3: invalid number for message id.");
+
+        let err = DbcError::UnknownReference("message 1234".to_string());
+        assert_eq!(err.to_string(), "Unknown reference to message 1234");
+
+        #[cfg(feature = "std")]
+        {
+            let err = DbcError::from(io::Error::new(io::ErrorKind::NotFound, "missing.dbc"));
+            assert_eq!(err.to_string(), "I/O error: missing.dbc");
+        }
+
+        #[cfg(feature = "serde")]
+        {
+            let err = DbcError::from(serde_json::from_str::<u32>("x").unwrap_err());
+            assert!(matches!(err, DbcError::SerializationError(_)));
+            assert!(err.to_string().starts_with("Serialization error: "));
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn run_missing_file() {
+        let config = Config { filenames: vec!["does_not_exist.dbc".to_string()], format: OutputFormat::Debug, id_range: None };
+        assert!(matches!(run(config), Err(DbcError::IoError(_))));
+    }
+
+    #[test]
+    fn signal_range() {
+        let setup = Setup::new();
+        let messages = parse(setup.test_messages).unwrap().messages;
+        assert_eq!(messages[1].signals[0].range(), Some((-214.7483648, 214.7483647)));
+
+        let content = "SG_ sig : 0|8@1+ (1,0) [0|0] \"\" Vector__XXX";
+        assert_eq!(parse_signal(content).unwrap().range(), None);
+    }
+
+    #[test]
+    fn validate_fixture() {
+        let setup = Setup::new();
+        assert!(parse(setup.test_messages).unwrap().validate().is_empty());
+    }
+
+    #[test]
+    fn validate_signal_out_of_bounds() {
+        let content = "
+BO_ 100 Short: 2 Vector__XXX
+ SG_ too_long : 8|16@1+ (1,0) [0|0] \"\" Vector__XXX
+ SG_ motorola : 7|16@0+ (1,0) [0|0] \"\" Vector__XXX
+";
+        let errors = parse(content).unwrap().validate();
+        assert_eq!(errors, vec![
+            ValidationError::SignalOutOfBounds { message: "Short".to_string(), signal: "too_long".to_string() },
+            ValidationError::OverlappingSignals {
+                message: "Short".to_string(), first: "too_long".to_string(), second: "motorola".to_string()
+            }
+        ]);
+        assert_eq!(errors[0].to_string(), "Signal too_long exceeds the size of message Short");
+    }
+
+    #[test]
+    fn validate_duplicates_and_overlaps() {
+        let content = "
+BO_ 100 First: 8 Vector__XXX
+ SG_ mux M : 0|8@1+ (1,0) [0|0] \"\" Vector__XXX
+ SG_ a m1 : 8|8@1+ (1,0) [0|0] \"\" Vector__XXX
+ SG_ b m2 : 8|8@1+ (1,0) [0|0] \"\" Vector__XXX
+ SG_ c : 12|8@1+ (1,0) [0|0] \"\" Vector__XXX
+
+BO_ 100 Second: 8 Vector__XXX
+";
+        let errors = parse(content).unwrap().validate();
+        assert_eq!(errors, vec![
+            ValidationError::OverlappingSignals {
+                message: "First".to_string(), first: "a".to_string(), second: "c".to_string()
+            },
+            ValidationError::OverlappingSignals {
+                message: "First".to_string(), first: "b".to_string(), second: "c".to_string()
+            },
+            ValidationError::DuplicateMessageId { id: 100, first: "First".to_string(), second: "Second".to_string() }
+        ]);
+    }
+
+    #[test]
+    fn can_fd_message() {
+        let content = "
+BO_ 2147484672 FdMessage: 64 Vector__XXX
+ SG_ fd_signal : 400|16@1+ (0.5,0) [0|0] \"\" Vector__XXX
+ SG_ fd_motorola : 511|8@0- (1,0) [0|0] \"\" Vector__XXX
+";
+        let message = &parse(content).unwrap().messages[0];
+        let mut data = [0u8; 64];
+        data[50] = 0x34;
+        data[51] = 0x12;
+        data[63] = 0xfe;
+        let values = message.decode(&data);
+        assert_eq!(values["fd_signal"], 2330.0);
+        assert_eq!(values["fd_motorola"], -2.0);
+
+        let encoded = message.encode(&values);
+        assert_eq!(encoded.len(), 64);
+        assert_eq!(encoded, data);
+    }
+
+    #[test]
+    fn decode_ignores_bytes_past_message_size() {
+        let content = "
+BO_ 100 Short: 1 Vector__XXX
+ SG_ sig : 0|16@1+ (1,0) [0|0] \"\" Vector__XXX
+";
+        let message = &parse(content).unwrap().messages[0];
+        assert_eq!(message.decode(&[0x01, 0x02])["sig"], 1.0);
+    }
+
+    #[test]
+    fn extended_can_id() {
+        let setup = Setup::new();
+        let messages = parse(setup.test_messages).unwrap().messages;
+        assert!(messages[0].is_extended());
+        assert_eq!(messages[0].can_id(), 0x18F3_DA03);
+
+        let message = parse_message("BO_ 1024 Standard: 8 Vector__XXX").unwrap();
+        assert!(!message.is_extended());
+        assert_eq!(message.can_id(), 0x400);
+    }
+
+    #[test]
+    fn message_attributes() {
+        let setup = Setup::new();
+        let contents = format!("{}
+BA_DEF_ BO_ \"GenMsgCycleTime\" INT 0 10000;
+BA_ \"GenMsgCycleTime\" BO_ 2565986819 100;
+BA_ \"GenMsgCycleTime\" BO_ 1234 100;
+", setup.test_messages);
+        let dbc = parse(&contents).unwrap();
+        assert_eq!(dbc.attribute_definition("GenMsgCycleTime"), Some(&AttributeDefinition {
+            name: "GenMsgCycleTime".to_string(),
+            object: AttributeObject::Message,
+            value_type: AttributeType::Int { min: 0, max: 10000 },
+            default: None
+        }));
+        assert_eq!(dbc.messages[2].attribute("GenMsgCycleTime"), Some(&AttributeValue::Int(100)));
+        assert_eq!(dbc.messages[0].attribute("GenMsgCycleTime"), None);
+    }
+
+    #[test]
+    fn attribute_targets() {
+        let setup = Setup::new();
+        let contents = format!("{}
+BA_DEF_ \"BusType\" STRING ;
+BA_DEF_ BU_ \"NodeLayerModules\" STRING ;
+BA_DEF_ SG_ \"GenSigStartValue\" FLOAT 0 100000;
+BA_DEF_ BO_ \"GenMsgSendType\" ENUM \"Cyclic\",\"Event\";
+BA_ \"BusType\" \"CAN\";
+BA_ \"NodeLayerModules\" BU_ TCU \"CANoeILNVector.dll\";
+BA_ \"GenSigStartValue\" SG_ 2565921559 gps_latitude 5;
+BA_ \"GenMsgSendType\" BO_ 2566117891 1;
+", setup.test_messages);
+        let dbc = parse(&contents).unwrap();
+        assert_eq!(dbc.attributes["BusType"], AttributeValue::String("CAN".to_string()));
+        assert_eq!(dbc.node_attributes["TCU"]["NodeLayerModules"],
+            AttributeValue::String("CANoeILNVector.dll".to_string()));
+        assert_eq!(dbc.messages[1].signals[1].attribute("GenSigStartValue"), Some(&AttributeValue::Float(5.0)));
+        assert_eq!(dbc.messages[0].attribute("GenMsgSendType"), Some(&AttributeValue::Int(1)));
+        assert_eq!(dbc.attribute_definition("GenMsgSendType").unwrap().value_type,
+            AttributeType::Enum(vec!["Cyclic".to_string(), "Event".to_string()]));
+
+        assert_eq!(parse(&dbc.to_dbc_string()).unwrap(), dbc);
+    }
+
+    #[test]
+    fn invalid_attribute_definition() {
+        let content = "BA_DEF_ BO_ \"GenMsgCycleTime\" INT 0;";
+        assert_eq!(parse_type::<AttributeDefinition>(content).err().unwrap(), DbcError::InvalidContent);
+    }
+
+    #[test]
+    fn header_block() {
+        let setup = Setup::new();
+        let contents = format!("VERSION \"Generated by blabla\"
+
+
+NS_ : 
+\tNS_DESC_
+\tCM_
+\tBA_DEF_
+\tBA_
+\tVAL_
+\tBA_DEF_DEF_
+\tEV_DATA_
+\tSIG_GROUP_
+\tBO_TX_BU_
+\tSG_MUL_VAL_
+
+BS_:
+
+BA_DEF_DEF_ \"GenMsgCycleTime\" 100;
+EV_ Dummy: 0 [0|1] \"\" 0 1 DUMMY_NODE_VECTOR0 Vector__XXX;
+{}", setup.test_messages);
+        let dbc = parse(&contents).unwrap();
+        assert_eq!(dbc.nodes.len(), 2);
+        assert_eq!(dbc.messages.len(), 3);
+        assert_eq!(dbc.messages[0].signals.len(), 4);
+    }
+
+    #[test]
+    fn test_file() {
+        let contents = std::fs::read_to_string("test.dbc").unwrap();
+        let dbc = parse(&contents).unwrap();
+        let setup = Setup::new();
+        let expected = parse(setup.test_messages).unwrap();
+        assert_eq!(dbc.version, Some(" Generated by blabla".to_string()));
+        assert_eq!(dbc.nodes, expected.nodes);
+        assert_eq!(dbc.messages, expected.messages);
+    }
+
+    #[test]
+    fn physical_conversion() {
+        let setup = Setup::new();
+        let messages = parse(setup.test_messages).unwrap().messages;
+        let gps_longitude = &messages[1].signals[0];
+        assert_eq!(gps_longitude.raw_to_physical(10_000_000), 1.0);
+        assert_eq!(gps_longitude.physical_to_raw(1.0), 10_000_000);
+        assert_eq!(gps_longitude.physical_to_raw(-214.7483648), -2147483648);
+
+        let signal = parse_signal("SG_ sig : 0|8@1+ (0.5,-40) [-40|87.5] \"degC\" Vector__XXX").unwrap();
+        assert_eq!(signal.raw_to_physical(100), 10.0);
+        assert_eq!(signal.physical_to_raw(10.2), 100);
+    }
+
+    #[test]
+    fn from_str() {
+        let setup = Setup::new();
+        let dbc: Dbc = setup.test_messages.parse().unwrap();
+        assert_eq!(dbc, parse(setup.test_messages).unwrap());
+        assert!("BO_ 2566117891 MsgDummy1: Vector__XXX".parse::<Dbc>().is_err());
+    }
+
+    #[test]
+    fn signal_by_name() {
+        let setup = Setup::new();
+        let mut dbc = parse(setup.test_messages).unwrap();
+        let signal = dbc.messages[1].signal_by_name("gps_latitude").unwrap();
+        assert_eq!(signal.start_bit, 7);
+        assert!(dbc.messages[1].signal_by_name("dummy1sg1").is_none());
+
+        dbc.messages[1].signal_by_name_mut("gps_latitude").unwrap().unit = "rad".to_string();
+        assert_eq!(dbc.messages[1].signals[1].unit, "rad");
+    }
+
+    #[test]
+    fn version() {
+        let setup = Setup::new();
+        let contents = format!("VERSION \"1.0\"\n{}", setup.test_messages);
+        let dbc = parse(&contents).unwrap();
+        assert_eq!(dbc.version, Some("1.0".into()));
+        assert_eq!(parse(&dbc.to_dbc_string()).unwrap(), dbc);
+
+        assert_eq!(parse(setup.test_messages).unwrap().version, None);
+        assert_eq!(parse("VERSION \"\"").unwrap().version, None);
+    }
+
+    #[test]
+    fn parse_warnings() {
+        let setup = Setup::new();
+        let contents = format!("{}
+BA_DEF_REL_ BU_SG_REL_ \"Dummy\" INT 0 1;
+", setup.test_messages);
+        let dbc = parse(&contents).unwrap();
+        assert_eq!(dbc.warnings, vec![ParseWarning {
+            line: 17,
+            content: "BA_DEF_REL_ BU_SG_REL_ \"Dummy\" INT 0 1;".to_string()
+        }]);
+        assert!(dbc.warnings[0].to_string().starts_with("Line 17 was not parsed: BA_DEF_REL_ BU_SG_REL_"));
+    }
+
+    #[test]
+    fn parse_warnings_unknown_line() {
+        let dbc = parse("BU_: TCU\nFOO_ bar;\n").unwrap();
+        assert_eq!(dbc.warnings, vec![ParseWarning { line: 2, content: "FOO_ bar;".to_string() }]);
+        assert!(parse(Setup::new().test_messages).unwrap().warnings.is_empty());
+    }
+
+    #[test]
+    fn back_to_back_messages() {
+        let content = "
+BO_ 100 First: 8 Vector__XXX
+ SG_ first_sig : 0|8@1+ (1,0) [0|0] \"\" Vector__XXX
+BO_ 200 Empty: 8 Vector__XXX
+BO_ 300 Third: 8 Vector__XXX
+ SG_ third_sig1 : 0|8@1+ (1,0) [0|0] \"\" Vector__XXX
+ SG_ third_sig2 : 8|8@1+ (1,0) [0|0] \"\" Vector__XXX
+CM_ BO_ 300 \"Directly after the signals\";
+";
+        let messages = parse(content).unwrap().messages;
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0].signals.len(), 1);
+        assert_eq!(messages[0].signals[0].name, "first_sig");
+        assert!(messages[1].signals.is_empty());
+        assert_eq!(messages[2].signals.len(), 2);
+        assert_eq!(messages[2].signals[0].name, "third_sig1");
+        assert_eq!(messages[2].comment, Some("Directly after the signals".to_string()));
+    }
+
+    #[test]
+    fn node_queries() {
+        let setup = Setup::new();
+        let contents = setup.test_messages
+            .replace("dummy1sg1 : 34|2@1+ (1,0) [0|3] \"kkk\" Vector__XXX", "dummy1sg1 : 34|2@1+ (1,0) [0|3] \"kkk\" VEHICLE")
+            .replace("[0|8191.875] \"\" Vector__XXX", "[0|8191.875] \"\" TCU,VEHICLE");
+        let dbc = parse(&contents).unwrap();
+
+        let sent: Vec<&str> = dbc.messages_sent_by("TCU").iter().map(|message| message.name.as_str()).collect();
+        assert_eq!(sent, vec!["MsgDummy3"]);
+        assert!(dbc.messages_sent_by("VEHICLE").is_empty());
+
+        let received: Vec<&str> = dbc.signals_received_by("VEHICLE").iter().map(|signal| signal.name.as_str()).collect();
+        assert_eq!(received, vec!["dummy1sg1", "dummy3sg1"]);
+        assert_eq!(dbc.signals_received_by("TCU").len(), 1);
+    }
+
+    #[test]
+    fn escaped_unit() {
+        let content = r#"SG_ temp : 0|8@1+ (1,-40) [-40|215] "deg\"C" TCU,VEHICLE"#;
+        let signal = parse_signal(content).unwrap();
+        assert_eq!(signal.unit, "deg\"C");
+        assert_eq!(signal.receivers, vec!["TCU", "VEHICLE"]);
+        assert_eq!(signal.to_string(), content);
+
+        let content = r#"SG_ temp : 0|8@1+ (1,-40) [-40|215] "" VEHICLE"#;
+        let signal = parse_signal(content).unwrap();
+        assert_eq!(signal.unit, "");
+        assert_eq!(signal.receivers, vec!["VEHICLE"]);
+    }
+
+    #[test]
+    fn escaped_unit_in_message() {
+        let content = r#"
+BO_ 100 Temperatures: 8 Vector__XXX
+ SG_ temp : 0|8@1+ (1,-40) [-40|215] "deg\"C" VEHICLE
+ SG_ other : 8|8@1+ (1,0) [0|0] "\\" VEHICLE
+"#;
+        let message = &parse(content).unwrap().messages[0];
+        assert_eq!(message.signals.len(), 2);
+        assert_eq!(message.signals[0].unit, "deg\"C");
+        assert_eq!(message.signals[1].unit, "\\");
+    }
+
+    #[test]
+    fn builder() {
+        let mut speed = Signal::new("speed", 0, 16);
+        speed.factor = 0.01;
+        speed.value_max = 655.35;
+        speed.unit = "km/h".to_string();
+        speed.receivers = vec!["VEHICLE".to_string()];
+
+        let mut gear = Signal::new("gear", 7, 4);
+        gear.is_little_endian = false;
+        gear.is_signed = true;
+
+        let mut transmission = Message::new(200, "Transmission", 2);
+        transmission.transmitters = vec!["TCU".to_string()];
+
+        let dbc = DbcBuilder::new()
+            .version("1.0")
+            .add_node("TCU")
+            .add_node("VEHICLE")
+            .add_message(Message::new(100, "Speed", 8))
+            .add_message(transmission)
+            .add_signal_to(100, speed).unwrap()
+            .add_signal_to(200, gear).unwrap()
+            .build();
+
+        assert_eq!(dbc.messages.len(), 2);
+        assert_eq!(dbc.message_by_id(200).unwrap().signals[0].name, "gear");
+        assert_eq!(parse(&dbc.to_dbc_string()).unwrap(), dbc);
+    }
+
+    #[test]
+    fn builder_unknown_message() {
+        let err = DbcBuilder::new()
+            .add_message(Message::new(100, "Speed", 8))
+            .add_signal_to(200, Signal::new("speed", 0, 16))
+            .err().unwrap();
+        assert_eq!(err, DbcError::UnknownReference("message 200".to_string()));
+    }
+
+    #[test]
+    fn signal_groups() {
+        let setup = Setup::new();
+        let contents = format!("{}
+SIG_GROUP_ 2565921559 gps_position 1 : gps_latitude gps_longitude;
+SIG_GROUP_ 1234 unknown 1 : gps_latitude;
+", setup.test_messages);
+        let dbc = parse(&contents).unwrap();
+        assert_eq!(dbc.messages[1].signal_groups, vec![SignalGroup {
+            name: "gps_position".to_string(),
+            repetitions: 1,
+            signals: vec!["gps_latitude".to_string(), "gps_longitude".to_string()]
+        }]);
+        assert!(dbc.messages[0].signal_groups.is_empty());
+        assert_eq!(parse(&dbc.to_dbc_string()).unwrap(), dbc);
+    }
+
+    #[test]
+    fn iter_signals() {
+        let setup = Setup::new();
+        let dbc = parse(setup.test_messages).unwrap();
+        assert_eq!(dbc.iter_signals().count(), 7);
+
+        let deg: Vec<(&str, &str)> = dbc.iter_signals()
+            .filter(|(_, signal)| signal.unit == "deg")
+            .map(|(message, signal)| (message.name.as_str(), signal.name.as_str()))
+            .collect();
+        assert_eq!(deg, vec![("MsgDummy2", "gps_longitude"), ("MsgDummy2", "gps_latitude")]);
+    }
+
+    #[test]
+    fn duplicate_nodes() {
+        let dbc = parse("BU_: TCU VEHICLE\nBU_:   ECU3  tcu\tVEHICLE\n").unwrap();
+        assert_eq!(dbc.node_names(), vec!["TCU", "VEHICLE", "ECU3"]);
+    }
+
+    #[test]
+    fn mask_and_shift() {
+        let setup = Setup::new();
+        let messages = parse(setup.test_messages).unwrap().messages;
+        let dummy1sg1 = &messages[0].signals[0];
+        assert_eq!(dummy1sg1.mask(), 0x3);
+        assert_eq!(dummy1sg1.shift(), 34);
+
+        let data = [0xd3, 0x48, 0xf8, 0xff, 0x0b, 0x00, 0x00, 0x00];
+        let frame = u64::from_le_bytes(data);
+        assert_eq!(frame >> dummy1sg1.shift() & dummy1sg1.mask(), 2);
+
+        let gps_latitude = &messages[1].signals[1];
+        let data = [0x1c, 0xaf, 0x0e, 0x87, 0xf9, 0x25, 0x3d, 0x40];
+        let frame = u64::from_be_bytes(data);
+        assert_eq!(gps_latitude.mask(), 0xFFFF_FFFF);
+        assert_eq!(gps_latitude.shift(), 32);
+        assert_eq!(frame >> gps_latitude.shift() & gps_latitude.mask(), 0x1caf0e87);
+    }
+
+    #[test]
+    fn invalid_field_reason() {
+        let content = "
+BO_ 2566117891 MsgDummy1: 8 Vector__XXX
+ SG_ dummy1sg1 : 34|2@+ (1,0) [0|3] \"kkk\" Vector__XXX
+";
+        match parse(content).err().unwrap() {
+            DbcError::InvalidLine { line, reason, .. } => {
+                assert_eq!(line, 3);
+                assert!(reason.contains("byte-order"));
+            },
+            err => panic!("unexpected error {:?}", err),
+        }
+
+        let content = "SG_ dummy1sg1 : 34|2@1+ (1,0) [0|3] kkk Vector__XXX";
+        assert_eq!(invalid_field(content, &SIGNAL_FIELDS), "Invalid signal, expected the quoted unit");
+
+        let content = "BO_ MsgDummy1: 8 Vector__XXX";
+        assert_eq!(invalid_field(content, &MESSAGE_FIELDS), "Invalid message start, expected a numeric message id");
+    }
+
+    #[test]
+    fn parse_tab_separated_signal() {
+        let content = "SG_\tdummy1sg1\t:\t34|2@1+\t(1,0)\t[0|3]\t\"kkk\"\tVector__XXX";
+        assert_eq!(parse_signal(content).unwrap(), parse_signal("SG_ dummy1sg1 : 34|2@1+ (1,0) [0|3] \"kkk\" Vector__XXX").unwrap());
+
+        let content = "
+BO_ 2566117891 MsgDummy1: 8 Vector__XXX
+\tSG_  sig_a  m1  :  8|8@1+  (1,0)  [0|255]  \"\"  TCU
+\t\tSG_\tsig_b : 16|8@1+ (1,0) [0|255] \"\" VEHICLE
+";
+        let dbc = parse(content).unwrap();
+        let message = &dbc.messages[0];
+        assert_eq!(message.signals.len(), 2);
+        assert_eq!(message.signals[0].multiplexer, MultiplexIndicator::MultiplexedBy(1));
+        assert_eq!(message.signals[0].receivers, vec!["TCU"]);
+        assert_eq!(message.signals[1].name, "sig_b");
+    }
+
+    #[test]
+    fn bit_layout() {
+        let setup = Setup::new();
+        let messages = parse(setup.test_messages).unwrap().messages;
+        let layout = messages[0].bit_layout();
+        assert_eq!(layout.len(), 8);
+        assert_eq!(layout[0][0], Some("dummy1sg4"));
+        assert_eq!(layout[0][1], Some("dummy1sg4"));
+        assert_eq!(layout[4][2], Some("dummy1sg1"));
+        assert_eq!(layout[4][3], Some("dummy1sg1"));
+        assert_eq!(layout[4][4], None);
+        assert_eq!(layout[7], [None; 8]);
+    }
+
+    #[test]
+    fn parse_env_vars() {
+        let setup = Setup::new();
+        let contents = format!("{}
+EV_ EngineTemp: 1 [-40|215.5] \"degC\" 20 3 DUMMY_NODE_VECTOR3 TCU,VEHICLE;
+EV_ Label: 2 [0|0] \"\" 0 4 DUMMY_NODE_VECTOR8001 Vector__XXX;
+", setup.test_messages);
+        let dbc = parse(&contents).unwrap();
+        assert!(dbc.warnings.is_empty());
+        assert_eq!(dbc.env_vars, vec![
+            EnvVar {
+                name: "EngineTemp".to_string(),
+                var_type: EnvVarType::Float,
+                minimum: -40.0,
+                maximum: 215.5,
+                unit: "degC".to_string(),
+                initial_value: 20.0,
+                id: 3,
+                access_type: EnvVarAccess::ReadWrite,
+                access_nodes: vec!["TCU".to_string(), "VEHICLE".to_string()]
+            },
+            EnvVar {
+                name: "Label".to_string(),
+                var_type: EnvVarType::String,
+                minimum: 0.0,
+                maximum: 0.0,
+                unit: String::new(),
+                initial_value: 0.0,
+                id: 4,
+                access_type: EnvVarAccess::Read,
+                access_nodes: Vec::new()
+            }
+        ]);
+        assert_eq!(parse(&dbc.to_dbc_string()).unwrap(), dbc);
+
+        assert!(parse("EV_ Broken: 0 [0|1];").is_err());
+    }
+
+    #[test]
+    fn parse_nodes_without_tag() {
+        let nodes = parse_nodes("BU_: TCU VEHICLE ECU3").unwrap();
+        let names: Vec<&str> = nodes.iter().map(|node| node.name.as_str()).collect();
+        assert_eq!(names, vec!["TCU", "VEHICLE", "ECU3"]);
+
+        assert_eq!(parse_nodes("BU_:TCU\tVEHICLE").unwrap().len(), 2);
+        assert!(parse_nodes("BU_:").unwrap().is_empty());
+        assert_eq!(parse_nodes("BU_SG_REL_ 1 TCU").err(), Some(DbcError::WrongType));
+        assert_eq!(parse_nodes("BU_: TCU VEHICLE;").err(), Some(DbcError::InvalidContent));
+    }
+
+    #[test]
+    fn encode_start_value() {
+        let content = "
+BO_ 100 Status: 4 Vector__XXX
+ SG_ mode : 0|8@1+ (1,0) [0|255] \"\" Vector__XXX
+ SG_ level : 8|8@1- (0.5,0) [-64|63.5] \"\" Vector__XXX
+ SG_ counter : 16|8@1+ (1,0) [0|255] \"\" Vector__XXX
+
+BA_DEF_ SG_ \"GenSigStartValue\" INT 0 255;
+BA_ \"GenSigStartValue\" SG_ 100 mode 3;
+BA_ \"GenSigStartValue\" SG_ 100 level -2;
+BA_ \"GenSigStartValue\" SG_ 100 counter 9;
+";
+        let messages = parse(content).unwrap().messages;
+        assert_eq!(messages[0].signals[0].start_value(), Some(3));
+
+        let mut values = HashMap::new();
+        values.insert("counter".to_string(), 1.0);
+        assert_eq!(messages[0].encode(&values), vec![0x03, 0xfe, 0x01, 0x00]);
+    }
+
+    #[test]
+    fn encode_float_start_value() {
+        let content = "
+BO_ 100 Status: 8 Vector__XXX
+ SG_ temperature : 0|32@1- (0.5,0) [-100|100] \"\" Vector__XXX
+ SG_ counter : 32|8@1+ (1,0) [0|255] \"\" Vector__XXX
+
+BA_DEF_ SG_ \"GenSigStartValue\" INT 0 255;
+BA_ \"GenSigStartValue\" SG_ 100 temperature 3;
+SIG_VALTYPE_ 100 temperature : 1;
+";
+        let message = &parse(content).unwrap().messages[0];
+        assert_eq!(message.signals[0].value_type, ValueType::Float);
+
+        let data = message.encode(&HashMap::new());
+        assert_eq!(data[..4], 3f32.to_le_bytes());
+        assert_eq!(message.signals[0].decode(&data), 1.5);
+    }
+
+    #[test]
+    fn merge() {
+        let setup = Setup::new();
+        let mut dbc = parse(setup.test_messages).unwrap();
+        let other = parse("
+BU_: VEHICLE BODY
+
+BO_ 300 BodyStatus: 2 BODY
+ SG_ doors : 0|4@1+ (1,0) [0|15] \"\" VEHICLE
+SGTYPE_ Unsupported : 8@1+;
+").unwrap();
+        assert_eq!(other.warnings.len(), 1);
+
+        dbc.merge(other).unwrap();
+        assert!(dbc.warnings.is_empty());
+        let names: Vec<&str> = dbc.nodes.iter().map(|node| node.name.as_str()).collect();
+        assert_eq!(names, vec!["TCU", "VEHICLE", "BODY"]);
+        assert_eq!(dbc.messages.len(), 4);
+        assert_eq!(dbc.message_by_id(300).unwrap().name, "BodyStatus");
+        assert_eq!(dbc.message_by_id(300).unwrap().signals[0].name, "doors");
+    }
+
+    #[test]
+    fn merge_duplicate_id() {
+        let setup = Setup::new();
+        let mut dbc = parse(setup.test_messages).unwrap();
+        let other = parse("
+BO_ 300 BodyStatus: 2 BODY
+
+BO_ 2566117891 Duplicate: 8 Vector__XXX
+").unwrap();
+
+        assert_eq!(dbc.merge(other), Err(DbcError::DuplicateMessageId(2566117891)));
+        assert_eq!(dbc, parse(setup.test_messages).unwrap());
+        assert_eq!(DbcError::DuplicateMessageId(1).to_string(), "Duplicate message id 1");
+    }
+
+    #[test]
+    fn clone_dbc() {
+        let setup = Setup::new();
+        let dbc = parse(setup.test_messages).unwrap();
+        let mut copy = dbc.clone();
+        assert_eq!(copy, dbc);
+
+        copy.messages[0].name = "Renamed".to_string();
+        copy.nodes.push(dbc.nodes[0].clone());
+        assert_eq!(dbc.messages[0].name, "MsgDummy1");
+        assert_eq!(dbc.nodes.len(), 2);
+        assert_ne!(copy, dbc);
+    }
+
+    #[test]
+    fn decode_full_width_signed() {
+        let signal = parse_signal("SG_ sig : 8|32@1- (1,0) [0|0] \"\" Vector__XXX").unwrap();
+        assert_eq!(signal.decode(&[0x00, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00]), -1.0);
+
+        let signal = parse_signal("SG_ sig : 7|32@0- (0.5,10) [0|0] \"\" Vector__XXX").unwrap();
+        assert_eq!(signal.decode(&[0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00]), 9.5);
+        assert_eq!(signal.decode(&[0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]), -2147483648.0 * 0.5 + 10.0);
+
+        let signal = parse_signal("SG_ sig : 0|64@1- (1,0) [0|0] \"\" Vector__XXX").unwrap();
+        assert_eq!(signal.decode(&[0xff; 8]), -1.0);
+
+        let signal = parse_signal("SG_ sig : 0|64@1+ (1,0) [0|0] \"\" Vector__XXX").unwrap();
+        assert_eq!(signal.decode(&[0xff; 8]), u64::MAX as f64);
+    }
+
+    #[test]
+    fn signal_overlaps() {
+        let content = "
+BO_ 100 Overlap: 8 Vector__XXX
+ SG_ low : 0|12@1+ (1,0) [0|0] \"\" Vector__XXX
+ SG_ high : 8|8@1+ (1,0) [0|0] \"\" Vector__XXX
+ SG_ motorola : 23|8@0+ (1,0) [0|0] \"\" Vector__XXX
+ SG_ mux M : 24|8@1+ (1,0) [0|0] \"\" Vector__XXX
+ SG_ sig_a m1 : 32|8@1+ (1,0) [0|0] \"\" Vector__XXX
+ SG_ sig_b m2 : 32|8@1+ (1,0) [0|0] \"\" Vector__XXX
+";
+        let messages = parse(content).unwrap().messages;
+        let signals = &messages[0].signals;
+        assert!(signals[0].overlaps(&signals[1]));
+        assert!(signals[1].overlaps(&signals[0]));
+        assert!(!signals[1].overlaps(&signals[2]));
+        assert!(!signals[4].overlaps(&signals[5]));
+
+        let pairs = messages[0].overlapping_signals();
+        assert_eq!(pairs.len(), 1);
+        assert_eq!((pairs[0].0.name.as_str(), pairs[0].1.name.as_str()), ("low", "high"));
+
+        let setup = Setup::new();
+        let messages = parse(setup.test_messages).unwrap().messages;
+        assert!(messages.iter().all(|message| message.overlapping_signals().is_empty()));
+    }
+
+    #[derive(Default)]
+    struct CountingVisitor {
+        nodes: usize,
+        messages: usize,
+        signals: Vec<(u32, String)>
+    }
+
+    impl DbcVisitor for CountingVisitor {
+        fn on_node(&mut self, _node: &Node) {
+            self.nodes += 1;
+        }
+
+        fn on_message(&mut self, _message: &Message) {
+            self.messages += 1;
+        }
+
+        fn on_signal(&mut self, message: &Message, signal: &Signal) {
+            self.signals.push((message.id, signal.name.clone()));
+        }
+    }
+
+    #[test]
+    fn parse_with_counting_visitor() {
+        let setup = Setup::new();
+        let mut visitor = CountingVisitor::default();
+        parse_with_visitor(setup.test_messages, &mut visitor).unwrap();
+        assert_eq!(visitor.nodes, 2);
+        assert_eq!(visitor.messages, 3);
+        assert_eq!(visitor.signals.len(), 7);
+        assert_eq!(visitor.signals[4], (2565921559, "gps_longitude".to_string()));
+
+        let mut visitor = CountingVisitor::default();
+        let contents = "BO_ 1 Msg: 8 Vector__XXX\n SG_ sig : 0|8@1+ (1,0) [0|0] Vector__XXX\n";
+        assert!(parse_with_visitor(contents, &mut visitor).is_err());
+    }
+
+    #[derive(Default)]
+    struct EventVisitor {
+        events: Vec<String>
+    }
+
+    impl DbcVisitor for EventVisitor {
+        fn on_node(&mut self, node: &Node) {
+            self.events.push(format!("node {}", node.name));
+        }
+
+        fn on_value_table(&mut self, name: &str, _descriptions: &HashMap<i64, String>) {
+            self.events.push(format!("table {}", name));
+        }
+
+        fn on_value_descriptions(&mut self, message_id: u32, signal_name: &str, descriptions: &HashMap<i64, String>) {
+            self.events.push(format!("values {} {} {}", message_id, signal_name, descriptions[&1]));
+        }
+
+        fn on_value_type(&mut self, message_id: u32, signal_name: &str, value_type: ValueType) {
+            self.events.push(format!("type {} {} {:?}", message_id, signal_name, value_type));
+        }
+
+        fn on_signal_comment(&mut self, message_id: u32, signal_name: &str, text: &str) {
+            self.events.push(format!("comment {} {} {}", message_id, signal_name, text));
+        }
+
+        fn on_message_attribute(&mut self, message_id: u32, name: &str, value: &AttributeValue) {
+            self.events.push(format!("attribute {} {} {}", message_id, name, value));
+        }
+
+        fn on_warning(&mut self, warning: &ParseWarning) {
+            self.events.push(format!("warning {}", warning.line));
+        }
+    }
+
+    #[test]
+    fn parse_with_visitor_reports_every_line() {
+        let contents = "BU_: TCU tcu VEHICLE
+VAL_TABLE_ States 1 \"On\" 0 \"Off\";
+BO_ 1 Msg: 8 TCU
+ SG_ sig : 0|32@1+ (1,0) [0|0] \"\" Vector__XXX
+
+CM_ SG_ 1 sig \"Signal comment\";
+BA_DEF_ BO_ \"GenMsgCycleTime\" INT 0 65535;
+BA_ \"GenMsgCycleTime\" BO_ 1 100;
+VAL_ 1 sig States;
+SIG_VALTYPE_ 1 sig : 1;
+SGTYPE_ Unsupported : 8@1+;
+";
+        let mut visitor = EventVisitor::default();
+        parse_with_visitor(contents, &mut visitor).unwrap();
+        assert_eq!(visitor.events, vec![
+            "node TCU", "node VEHICLE", "table States", "comment 1 sig Signal comment",
+            "attribute 1 GenMsgCycleTime 100", "values 1 sig On", "type 1 sig Float", "warning 11"
+        ]);
+
+        let dbc = parse(contents).unwrap();
+        let signal = &dbc.messages[0].signals[0];
+        assert_eq!(dbc.nodes.len(), 2);
+        assert_eq!(signal.comment.as_deref(), Some("Signal comment"));
+        assert_eq!(signal.value_descriptions[&0], "Off");
+        assert_eq!(signal.value_type, ValueType::Float);
+        assert_eq!(dbc.messages[0].attributes["GenMsgCycleTime"], AttributeValue::Int(100));
+        assert_eq!(dbc.warnings.len(), 1);
+    }
+
+    #[test]
+    fn parse_crlf() {
+        let setup = Setup::new();
+        let contents = format!("{}
+CM_ SG_ 2566117891 dummy1sg1 \"Ignition
+state\";
+", setup.test_messages).replace('\n', "\r\n");
+        let dbc = parse(&contents).unwrap();
+        assert_eq!(dbc.nodes, parse(setup.test_messages).unwrap().nodes);
+        assert_eq!(dbc.messages.len(), 3);
+        assert_eq!(dbc.messages[0].signals[0].unit, "kkk");
+        assert_eq!(dbc.messages[0].signals[0].comment, Some("Ignition\nstate".to_string()));
+
+        let contents = "BO_ 1 Msg: 8 TCU\r\r\n SG_ sig : 0|8@1+ (1,0) [0|0] \"V\" TCU,VEHICLE\r\r\n";
+        let message = &parse(contents).unwrap().messages[0];
+        assert_eq!(message.transmitters, vec!["TCU"]);
+        assert_eq!(message.signals[0].receivers, vec!["TCU", "VEHICLE"]);
+        assert_eq!(message.signals[0].unit, "V");
+    }
+
+    #[test]
+    fn statistics() {
+        let setup = Setup::new();
+        let contents = format!("{}
+BO_ 100 MuxMessage: 2 Vector__XXX
+ SG_ mux M : 0|8@1+ (1,0) [0|255] \"\" Vector__XXX
+ SG_ sig_a m1 : 8|8@1+ (1,0) [0|255] \"\" Vector__XXX
+ SG_ sig_b m2 : 8|8@1+ (1,0) [0|255] \"\" Vector__XXX
+", setup.test_messages);
+
+        assert_eq!(parse(setup.test_messages).unwrap().statistics(), DbcStats {
+            nodes: 2,
+            messages: 3,
+            signals: 7,
+            multiplexed_signals: 0,
+            min_message_size: Some(8),
+            max_message_size: Some(8)
+        });
+
+        let stats = parse(&contents).unwrap().statistics();
+        assert_eq!(stats.signals, 10);
+        assert_eq!(stats.multiplexed_signals, 2);
+        assert_eq!(stats.min_message_size, Some(2));
+        assert_eq!(parse("").unwrap().statistics().max_message_size, None);
+    }
+
+    #[test]
+    fn invalid_byte_order() {
+        let content = "SG_ dummy1sg1 : 34|2@2+ (1,0) [0|3] \"kkk\" Vector__XXX";
+        assert_eq!(parse_signal(content).err(), Some(DbcError::InvalidContent));
+
+        let content = format!("BO_ 1 Msg: 8 Vector__XXX\n {}\n", content);
+        match parse(&content).err().unwrap() {
+            DbcError::InvalidLine { line, reason, .. } => {
+                assert_eq!(line, 2);
+                assert_eq!(reason, "Invalid signal, expected byte-order digit `0` or `1` after `@`");
+            },
+            err => panic!("unexpected error {:?}", err),
+        }
+    }
+
+    #[test]
+    fn out_of_range_numbers() {
+        let number_parse = |field| Some(DbcError::NumberParse { line: 0, field });
+        assert_eq!(parse_message("BO_ 99999999999 Msg: 8 Vector__XXX").err(), number_parse("message id"));
+        assert_eq!(parse_message("BO_ 1 Msg: 300 Vector__XXX").err(), number_parse("message size"));
+        assert_eq!(parse_signal("SG_ sig : 70000|8@1+ (1,0) [0|0] \"\" Vector__XXX").err(), number_parse("start bit"));
+        assert_eq!(parse_signal("SG_ sig : 0|8@1+ (1.2.3,0) [0|0] \"\" Vector__XXX").err(), Some(DbcError::InvalidContent));
+
+        assert_eq!(parse("BO_ 1 Msg: 300 Vector__XXX").err(), Some(DbcError::NumberParse { line: 1, field: "message size" }));
+        assert_eq!(parse("VAL_ 1 sig 99999999999999999999 \"x\";").err(),
+            Some(DbcError::NumberParse { line: 1, field: "value description" }));
+        assert_eq!(parse("BA_ \"Attr\" BO_ 99999999999 1;").err(),
+            Some(DbcError::NumberParse { line: 1, field: "message id" }));
+    }
+
+    #[test]
+    fn signals_past_the_largest_frame() {
+        let invalid = Some(DbcError::InvalidContent);
+        assert_eq!(parse_signal("SG_ sig : 510|4@1+ (1,0) [0|0] \"\" Vector__XXX").err(), invalid);
+        assert_eq!(parse_signal("SG_ sig : 65535|65535@1+ (1,0) [0|0] \"\" Vector__XXX").err(), invalid);
+        assert_eq!(parse_signal("SG_ sig : 504|2@0+ (1,0) [0|0] \"\" Vector__XXX").err(), invalid);
+        assert_eq!(parse_signal("SG_ sig : 600|1@1+ (1,0) [0|0] \"\" Vector__XXX").err(), invalid);
+        assert!(parse_signal("SG_ sig : 508|4@1+ (1,0) [0|0] \"\" Vector__XXX").is_ok());
+        assert!(parse_signal("SG_ sig : 511|8@0+ (1,0) [0|0] \"\" Vector__XXX").is_ok());
+
+        let contents = "BO_ 1 Msg: 64 Vector__XXX\n SG_ sig : 510|4@1+ (1,0) [0|0] \"\" Vector__XXX\n";
+        assert_eq!(parse(contents).err(), Some(DbcError::InvalidLine {
+            line: 2,
+            content: " SG_ sig : 510|4@1+ (1,0) [0|0] \"\" Vector__XXX".to_string(),
+            reason: "Signal does not fit in the frame"
+        }));
+    }
+
+    #[test]
+    fn truncated_lines_never_panic() {
+        let setup = Setup::new();
+        let contents = format!("{}
+CM_ SG_ 2566117891 dummy1sg1 \"Ignition state\";
+BA_DEF_ BO_ \"GenMsgCycleTime\" INT 0 65535;
+BA_ \"GenMsgCycleTime\" BO_ 2566117891 100;
+VAL_ 2566117891 dummy1sg1 3 \"On\" 0 \"Off\";
+SIG_GROUP_ 2566117891 Group 1 : dummy1sg1;
+EV_ Dummy: 0 [0|1] \"\" 0 1 DUMMY_NODE_VECTOR0 Vector__XXX;
+", setup.test_messages);
+
+        for line in contents.lines() {
+            for end in (0..=line.len()).filter(|&end| line.is_char_boundary(end)) {
+                let truncated = &line[..end];
+                let contents = [
+                    format!("BO_ 1 Msg: 8 Vector__XXX\n{}\n", truncated),
+                    truncated.replace('1', "9999999999999999999999"),
+                ];
+                for dbc in contents.iter().filter_map(|contents| parse(contents).ok()) {
+                    let _ = dbc.validate();
+                    for message in &dbc.messages {
+                        let data = message.encode(&message.decode(&[0xFF; 64]));
+                        let _ = message.decode(&data);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn byte_aligned_decode() {
+        let setup = Setup::new();
+        let messages = parse(setup.test_messages).unwrap().messages;
+        let gps_longitude = &messages[1].signals[0];
+        assert!(gps_longitude.is_byte_aligned());
+        assert_eq!(gps_longitude.length_bytes(), Some(4));
+        assert!(!messages[0].signals[0].is_byte_aligned());
+        assert_eq!(messages[0].signals[0].length_bytes(), None);
+
+        let intel = parse_signal("SG_ sig : 16|16@1- (1,0) [0|0] \"\" Vector__XXX").unwrap();
+        assert_eq!(intel.length_bytes(), Some(2));
+
+        let data = [0x12, 0x34, 0x56, 0x78, 0xf9, 0xab, 0xcd, 0xef];
+        for signal in [gps_longitude, &messages[1].signals[1], &intel] {
+            assert_eq!(signal.raw_value(&data), signal.raw_value_bitwise(&data));
+        }
+        assert_eq!(gps_longitude.raw_value(&data), 0xf9abcdef);
+        assert_eq!(gps_longitude.raw_value(&data[..6]), gps_longitude.raw_value_bitwise(&data[..6]));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn parse_file_round_trip() {
+        let setup = Setup::new();
+        let path = std::env::temp_dir().join(format!("import_dbc_{}.dbc", std::process::id()));
+        std::fs::write(&path, setup.test_messages).unwrap();
+        let dbc = parse_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(dbc.unwrap(), parse(setup.test_messages).unwrap());
+        let err = parse_file(&path).unwrap_err();
+        assert!(matches!(err.downcast_ref::<DbcError>(), Some(DbcError::IoError(_))));
+    }
+
+    #[test]
+    fn signal_csv() {
+        let setup = Setup::new();
+        let csv = parse(setup.test_messages).unwrap().to_signal_csv();
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows[0], "message_id,message_name,signal_name,start_bit,size,byte_order,signed,factor,offset,min,max,unit");
+        assert_eq!(rows.len(), 8);
+        assert_eq!(rows[1], "2566117891,MsgDummy1,dummy1sg1,34,2,Intel,false,1,0,0,3,kkk");
+        assert_eq!(rows[5], "2565921559,MsgDummy2,gps_longitude,39,32,Motorola,true,0.0000001,0,-214.7483648,214.7483647,deg");
+
+        assert_eq!(csv_field("m/s, \"avg\""), "\"m/s, \"\"avg\"\"\"");
+    }
+
+    #[test]
+    fn attribute_defaults() {
+        let setup = Setup::new();
+        let contents = format!("{}
+BA_DEF_ BO_ \"GenMsgCycleTime\" INT 0 10000;
+BA_DEF_ SG_ \"GenSigStartValue\" FLOAT 0 100000;
+BA_DEF_ \"BusType\" STRING ;
+BA_DEF_DEF_ \"GenMsgCycleTime\" 100;
+BA_DEF_DEF_ \"GenSigStartValue\" 5;
+BA_DEF_DEF_ \"BusType\" \"CAN\";
+BA_DEF_DEF_ \"Unknown\" 1;
+BA_ \"GenMsgCycleTime\" BO_ 2565986819 20;
+", setup.test_messages);
+        let dbc = parse(&contents).unwrap();
+        assert!(dbc.warnings.is_empty());
+        assert_eq!(dbc.attribute_definition("GenMsgCycleTime").unwrap().default, Some(AttributeValue::Int(100)));
+        assert_eq!(dbc.messages[0].attribute("GenMsgCycleTime"), Some(&AttributeValue::Int(100)));
+        assert_eq!(dbc.messages[2].attribute("GenMsgCycleTime"), Some(&AttributeValue::Int(20)));
+        assert_eq!(dbc.messages[1].signals[0].attribute("GenSigStartValue"), Some(&AttributeValue::Float(5.0)));
+        assert_eq!(dbc.attributes["BusType"], AttributeValue::String("CAN".to_string()));
+
+        let output = dbc.to_dbc_string();
+        assert!(output.contains("BA_DEF_DEF_ \"GenMsgCycleTime\" 100;"));
+        assert!(!output.contains("BA_ \"GenMsgCycleTime\" BO_ 2566117891"));
+        assert_eq!(parse(&output).unwrap(), dbc);
+    }
+
+    #[test]
+    fn message_cycle_time() {
+        let setup = Setup::new();
+        let contents = format!("{}
+BA_DEF_ BO_ \"GenMsgCycleTime\" INT 0 10000;
+BA_ \"GenMsgCycleTime\" BO_ 2565986819 20;
+", setup.test_messages);
+        let dbc = parse(&contents).unwrap();
+        assert_eq!(dbc.messages[2].cycle_time(), Some(20));
+        assert_eq!(dbc.messages[0].cycle_time(), None);
+
+        let dbc = parse(&format!("{}BA_DEF_DEF_ \"GenMsgCycleTime\" 100;\n", contents)).unwrap();
+        assert_eq!(dbc.messages[0].cycle_time(), Some(100));
+        assert_eq!(dbc.messages[2].cycle_time(), Some(20));
+    }
+
+    #[test]
+    fn decode_motorola_golden() {
+        let setup = Setup::new();
+        let messages = parse(setup.test_messages).unwrap().messages;
+        let values = messages[1].decode(&[0x1c, 0xb1, 0x27, 0x60, 0xf9, 0x18, 0xc9, 0xc0]);
+        assert!((values["gps_latitude"] - 48.1372).abs() < 1e-9);
+        assert!((values["gps_longitude"] - -11.5816).abs() < 1e-9);
+
+        // Not byte aligned, so the bits are gathered one by one: bits 5..0
+        // of the first byte followed by bits 7..2 of the second one
+        let signal = parse_signal("SG_ sig : 5|12@0+ (1,0) [0|0] \"\" Vector__XXX").unwrap();
+        assert_eq!(signal.decode(&[0x2a, 0xfc]), 2751.0);
+        let signal = parse_signal("SG_ sig : 5|12@0- (1,0) [0|0] \"\" Vector__XXX").unwrap();
+        assert_eq!(signal.decode(&[0x2a, 0xfc]), 2751.0 - 4096.0);
+    }
+
+    #[test]
+    fn sort_messages_by_id() {
+        let setup = Setup::new();
+        let contents = format!("{}
+BO_ 1024 Standard: 8 Vector__XXX
+", setup.test_messages);
+        let mut dbc = parse(&contents).unwrap();
+
+        let sorted: Vec<&str> = dbc.messages_sorted().iter().map(|message| message.name.as_str()).collect();
+        assert_eq!(sorted, vec!["Standard", "MsgDummy2", "MsgDummy3", "MsgDummy1"]);
+
+        dbc.sort_messages_by_id();
+        let ids: Vec<u32> = dbc.messages.iter().map(|message| message.can_id()).collect();
+        assert!(ids.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(dbc.messages[0].name, "Standard");
+        assert_eq!(dbc.message_by_id(2566117891).unwrap().name, "MsgDummy1");
+    }
+
+    #[test]
+    fn hex_and_negative_attribute_values() {
+        let contents = "
+BO_ 100 Msg: 8 Vector__XXX
+
+BA_DEF_ BO_ \"SomeAttr\" HEX 0 1023;
+BA_DEF_ BO_ \"Offset\" INT -100 100;
+BA_DEF_ \"Mask\" HEX 0 65535;
+BA_DEF_DEF_ \"Mask\" 0xFF00;
+BA_ \"SomeAttr\" BO_ 100 0x1FF;
+BA_ \"Offset\" BO_ 100 -42;
+";
+        let dbc = parse(contents).unwrap();
+        assert!(dbc.warnings.is_empty());
+        assert_eq!(dbc.messages[0].attribute("SomeAttr"), Some(&AttributeValue::Int(0x1FF)));
+        assert_eq!(dbc.messages[0].attribute("Offset"), Some(&AttributeValue::Int(-42)));
+        assert_eq!(dbc.attributes["Mask"], AttributeValue::Int(0xFF00));
+
+        assert_eq!(attribute_value("-0x10", None), AttributeValue::Int(-16));
+        assert_eq!(attribute_value("+7", None), AttributeValue::Int(7));
+    }
+
+    #[test]
+    fn encode_checked() {
+        let setup = Setup::new();
+        let messages = parse(setup.test_messages).unwrap().messages;
+        let dummy1sg1 = &messages[0].signals[0];
+
+        let mut data = [0u8; 8];
+        assert_eq!(dummy1sg1.encode_checked(2.0, &mut data), Ok(()));
+        assert_eq!(data[4], 0x08);
+
+        let err = dummy1sg1.encode_checked(4.0, &mut data).err().unwrap();
+        assert_eq!(err, EncodeError::OutOfRange { signal: "dummy1sg1".to_string(), value: 4.0, min: 0.0, max: 3.0 });
+        assert_eq!(err.to_string(), "Value 4 for signal dummy1sg1 is outside of [0|3]");
+        assert_eq!(data[4], 0x08);
+
+        // Without a declared range only the bit width applies
+        let signal = parse_signal("SG_ sig : 0|8@1+ (1,0) [0|0] \"\" Vector__XXX").unwrap();
+        assert_eq!(signal.encode_checked(200.0, &mut data), Ok(()));
+        assert_eq!(data[0], 200);
+    }
+
+    #[test]
+    fn extended_multiplexing() {
+        let content = "
+BO_ 100 MuxMessage: 8 Vector__XXX
+ SG_ mux M : 0|8@1+ (1,0) [0|255] \"\" Vector__XXX
+ SG_ sig_a m1 : 8|8@1+ (1,0) [0|255] \"\" Vector__XXX
+ SG_ sig_b m2 : 16|8@1+ (1,0) [0|255] \"\" Vector__XXX
+
+SG_MUL_VAL_ 100 sig_b mux 2-2, 4-6;
+";
+        let dbc = parse(content).unwrap();
+        assert!(dbc.warnings.is_empty());
+        let message = &dbc.messages[0];
+        assert_eq!(message.signals[2].extended_multiplex, Some(ExtendedMultiplex {
+            multiplexor: "mux".to_string(),
+            ranges: vec![(2, 2), (4, 6)]
+        }));
+        assert_eq!(message.signals[1].extended_multiplex, None);
+
+        assert!(message.decode(&[5, 1, 7, 0, 0, 0, 0, 0]).contains_key("sig_b"));
+        assert!(!message.decode(&[3, 1, 7, 0, 0, 0, 0, 0]).contains_key("sig_b"));
+        assert_eq!(parse(&dbc.to_dbc_string()).unwrap(), dbc);
+    }
+
+    #[test]
+    fn diff() {
+        let setup = Setup::new();
+        let old = parse(setup.test_messages).unwrap();
+        assert!(old.diff(&old).is_empty());
+
+        let mut new = old.clone();
+        new.messages.push(Message::new(300, "Added", 2));
+        new.messages[0].signals.retain(|signal| signal.name != "dummy1sg2");
+        new.messages[0].signals[0].start_bit = 40;
+        new.messages.remove(2);
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.added_messages, vec![300]);
+        assert_eq!(diff.removed_messages, vec![2565986819]);
+        assert_eq!(diff.changed_messages, vec![MessageDiff {
+            id: 2566117891,
+            changes: Vec::new(),
+            added_signals: Vec::new(),
+            removed_signals: vec!["dummy1sg2".to_string()],
+            changed_signals: vec![SignalDiff {
+                name: "dummy1sg1".to_string(),
+                changes: vec![FieldChange { field: "start_bit", old: "34".to_string(), new: "40".to_string() }]
+            }]
+        }]);
+    }
+
+    #[test]
+    fn active_signals() {
+        let content = "
+BO_ 100 MuxMessage: 8 Vector__XXX
+ SG_ mux M : 0|8@1+ (1,0) [0|255] \"\" Vector__XXX
+ SG_ sig_a m1 : 8|8@1+ (1,0) [0|255] \"\" Vector__XXX
+ SG_ sig_b m2 : 8|16@1+ (1,0) [0|65535] \"\" Vector__XXX
+ SG_ plain : 24|8@1+ (1,0) [0|255] \"\" Vector__XXX
+";
+        let messages = parse(content).unwrap().messages;
+        let message = &messages[0];
+        assert!(!message.signals[0].is_multiplexed());
+        assert!(message.signals[1].is_multiplexed());
+        assert!(!message.signals[3].is_multiplexed());
+
+        let names = |data: &[u8]| -> Vec<String> {
+            message.active_signals(data).iter().map(|signal| signal.name.clone()).collect()
+        };
+        assert_eq!(names(&[1, 0x2a, 0x01, 0x07, 0, 0, 0, 0]), vec!["mux", "sig_a", "plain"]);
+        assert_eq!(names(&[2, 0x2a, 0x01, 0x07, 0, 0, 0, 0]), vec!["mux", "sig_b", "plain"]);
+        assert_eq!(names(&[3, 0x2a, 0x01, 0x07, 0, 0, 0, 0]), vec!["mux", "plain"]);
+
+        let values = message.decode(&[2, 0x2a, 0x01, 0x07, 0, 0, 0, 0]);
+        assert_eq!(values["sig_b"], 298.0);
+        assert_eq!(values["plain"], 7.0);
+    }
+
+    #[test]
+    fn source_lines() {
+        let setup = Setup::new();
+        let dbc = parse(setup.test_messages).unwrap();
+        assert_eq!(dbc.message_by_name("MsgDummy2").unwrap().source_line, 10);
+        assert_eq!(dbc.messages[1].signals[1].source_line, 12);
+
+        let shifted = parse(&format!("\n\n{}", setup.test_messages)).unwrap();
+        assert_eq!(shifted.messages[1].source_line, 12);
+        assert_eq!(shifted, dbc);
+    }
+
+    #[test]
+    fn parse_with_bom() {
+        let setup = Setup::new();
+        let contents = format!("VERSION \"1.0\"\n{}", setup.test_messages);
+        let dbc = parse(&contents).unwrap();
+        let with_bom = parse(&format!("\u{feff}{}", contents)).unwrap();
+        assert_eq!(with_bom, dbc);
+        assert_eq!(with_bom.version, Some("1.0".to_string()));
+        assert!(with_bom.warnings.is_empty());
+
+        let nodes = parse("\u{feff}BU_: TCU VEHICLE").unwrap().nodes;
+        assert_eq!(nodes.len(), 2);
+    }
+
+    #[test]
+    fn parse_latin1_bytes() {
+        let mut contents = b"BO_ 1 Temperatures: 8 Vector__XXX\n SG_ oil : 0|8@1+ (1,-40) [-40|215] \"".to_vec();
+        contents.push(0xb0);
+        contents.extend_from_slice(b"C\" Vector__XXX\n");
+
+        let dbc = parse_bytes(&contents).unwrap();
+        assert_eq!(dbc.messages[0].signals[0].unit, "\u{b0}C");
+
+        let setup = Setup::new();
+        assert_eq!(parse_bytes(setup.test_messages.as_bytes()).unwrap(), parse(setup.test_messages).unwrap());
+        let utf8 = "BO_ 1 Temperatures: 8 Vector__XXX\n SG_ oil : 0|8@1+ (1,-40) [-40|215] \"\u{b0}C\" Vector__XXX\n";
+        assert_eq!(parse_bytes(utf8.as_bytes()).unwrap(), dbc);
+    }
+
+    #[test]
+    fn find_signal() {
+        let setup = Setup::new();
+        let contents = format!("{}
+BO_ 300 Copy: 8 Vector__XXX
+ SG_ gps_latitude : 7|32@0- (1E-007,0) [-214.7483648|214.7483647] \"deg\" Vector__XXX
+", setup.test_messages);
+        let dbc = parse(&contents).unwrap();
+
+        let (message, signal) = dbc.find_unique_signal("dummy3sg1").unwrap();
+        assert_eq!(message.name, "MsgDummy3");
+        assert_eq!(signal.start_bit, 16);
+
+        let found = dbc.find_signal("gps_latitude");
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].0.name, "MsgDummy2");
+        assert_eq!(found[1].0.name, "Copy");
+        assert_eq!(dbc.find_unique_signal("gps_latitude").err(), Some(DbcError::DuplicateSignalName("gps_latitude".to_string())));
+        assert_eq!(dbc.find_unique_signal("missing").err(), Some(DbcError::UnknownReference("signal missing".to_string())));
+    }
+
+    #[test]
+    fn node_comments() {
+        let setup = Setup::new();
+        let contents = format!("{}
+CM_ BU_ TCU \"Transmission control unit\";
+CM_ BU_ ECU3 \"Not in the node list\";
+", setup.test_messages);
+        let dbc = parse(&contents).unwrap();
+        assert!(dbc.warnings.is_empty());
+        assert_eq!(dbc.nodes[0].comment, Some("Transmission control unit".to_string()));
+        assert_eq!(dbc.nodes[1].comment, None);
+        assert_eq!(dbc.nodes.len(), 2);
+        assert_eq!(parse(&dbc.to_dbc_string()).unwrap(), dbc);
+    }
+
+    #[test]
+    fn parse_large_database() {
+        let mut contents = String::from("BU_: ECU\n");
+        for id in 0..200 {
+            contents.push_str(&format!("\nBO_ {} Message{}: 8 ECU\n", id, id));
+            for bit in 0..8 {
+                contents.push_str(&format!(" SG_ sig_{}_{} : {}|8@1+ (1,0) [0|255] \"\" Vector__XXX\n", id, bit, bit * 8));
+            }
+        }
+
+        let dbc = parse(&contents).unwrap();
+        assert_eq!(dbc.messages.len(), 200);
+        for (id, message) in dbc.messages.iter().enumerate() {
+            let names: Vec<String> = message.signals.iter().map(|signal| signal.name.clone()).collect();
+            let expected: Vec<String> = (0..8).map(|bit| format!("sig_{}_{}", id, bit)).collect();
+            assert_eq!(names, expected);
+        }
+        assert_eq!(parse(&dbc.to_dbc_string()).unwrap(), dbc);
+    }
+
+    #[test]
+    fn float_grammar() {
+        let signal = parse_signal("SG_ sig : 0|8@1+ (.5,5.) [-.5|1e3] \"\" Vector__XXX").unwrap();
+        assert_eq!(signal.factor, 0.5);
+        assert_eq!(signal.offset, 5.0);
+        assert_eq!(signal.value_min, -0.5);
+        assert_eq!(signal.value_max, 1000.0);
+
+        assert_eq!(parse_signal("SG_ sig : 0|8@1+ (1,0) [--3|0] \"\" Vector__XXX").err(), Some(DbcError::InvalidContent));
+        assert_eq!(parse_signal("SG_ sig : 0|8@1+ (1,-) [0|0] \"\" Vector__XXX").err(), Some(DbcError::InvalidContent));
+        assert_eq!(parse_signal("SG_ sig : 0|8@1+ (1.2.3,0) [0|0] \"\" Vector__XXX").err(), Some(DbcError::InvalidContent));
+
+        match parse("BO_ 1 Msg: 8 Vector__XXX\n SG_ sig : 0|8@1+ (1,0) [--3|0] \"\" Vector__XXX\n").err().unwrap() {
+            DbcError::InvalidLine { reason, .. } => assert_eq!(reason, "Invalid signal, expected the minimum value"),
+            err => panic!("unexpected error {:?}", err),
+        }
+    }
+
+    #[test]
+    fn signal_byte_order() {
+        let setup = Setup::new();
+        let dbc = parse(setup.test_messages).unwrap();
+        assert_eq!(dbc.messages[0].signals[0].byte_order(), ByteOrder::LittleEndian);
+        assert_eq!(dbc.messages[1].signals[0].byte_order(), ByteOrder::BigEndian);
+        assert_eq!(dbc.messages[1].signals[1].byte_order(), ByteOrder::BigEndian);
+    }
+
+    #[test]
+    fn remove_message() {
+        let setup = Setup::new();
+        let mut dbc = parse(setup.test_messages).unwrap();
+        let expected = dbc.messages[1].clone();
+
+        let removed = dbc.remove_message(2565921559).unwrap();
+        assert_eq!(removed, expected);
+        assert_eq!(dbc.messages.len(), 2);
+        assert!(dbc.message_by_id(2565921559).is_none());
+        assert_eq!(dbc.message_by_id(2565986819).unwrap().name, "MsgDummy3");
+        assert!(dbc.remove_message(2565921559).is_none());
+    }
+
+    #[test]
+    fn remove_signal() {
+        let setup = Setup::new();
+        let mut dbc = parse(setup.test_messages).unwrap();
+        let message = &mut dbc.messages[0];
+
+        let removed = message.remove_signal("dummy1sg2").unwrap();
+        assert_eq!(removed.name, "dummy1sg2");
+        assert_eq!(message.signals.len(), 3);
+        assert!(message.signal_by_name("dummy1sg2").is_none());
+        assert!(message.remove_signal("dummy1sg2").is_none());
+    }
+
+    #[test]
+    fn new_symbols_block() {
+        let contents = "VERSION \"\"
+
+NS_ :
+\tNS_DESC_
+\tCM_
+\tBA_DEF_
+\tBA_
+\tVAL_
+\tCAT_DEF_
+\tCAT_
+\tFILTER
+\tBA_DEF_DEF_
+\tEV_DATA_
+\tENVVAR_DATA_
+\tSGTYPE_
+\tSGTYPE_VAL_
+\tBA_DEF_SGTYPE_
+\tBA_SGTYPE_
+\tSIG_TYPE_REF_
+\tVAL_TABLE_
+\tSIG_GROUP_
+\tSIG_VALTYPE_
+\tSIGTYPE_VALTYPE_
+\tBO_TX_BU_
+\tBA_DEF_REL_
+\tBA_REL_
+\tBA_DEF_DEF_REL_
+\tBU_SG_REL_
+\tBU_EV_REL_
+\tBU_BO_REL_
+\tSG_MUL_VAL_
+BS_:
+
+BU_: TCU
+BO_ 1 Msg: 8 TCU
+ SG_ sig : 0|8@1+ (1,0) [0|0] \"\" Vector__XXX
+";
+        let dbc = parse(contents).unwrap();
+        assert!(dbc.warnings.is_empty());
+        assert_eq!(dbc.messages.len(), 1);
+        assert_eq!(dbc.messages[0].signals.len(), 1);
+
+        let mut visitor = CountingVisitor::default();
+        parse_with_visitor(contents, &mut visitor).unwrap();
+        assert_eq!(visitor.signals, vec![(1, "sig".to_string())]);
+    }
+
+    #[test]
+    fn iterate_messages() {
+        let setup = Setup::new();
+        let dbc = parse(setup.test_messages).unwrap();
+        let mut names = Vec::new();
+        for message in &dbc {
+            names.push(message.name.as_str());
+        }
+        assert_eq!(names, vec!["MsgDummy1", "MsgDummy2", "MsgDummy3"]);
+    }
+
+    #[test]
+    fn messages_without_signals() {
+        let contents = "BO_ 1 Reserved1: 8 Vector__XXX
+BO_ 2 Msg: 8 Vector__XXX
+ SG_ sig : 0|8@1+ (1,0) [0|0] \"\" Vector__XXX
+
+BO_ 3 Reserved2: 8 Vector__XXX
+
+BO_ 4 Reserved3: 8 Vector__XXX";
+        for contents in &[contents.to_string(), format!("{}\n", contents), format!("{}\n\n", contents)] {
+            let dbc = parse(contents).unwrap();
+            assert_eq!(dbc.messages.len(), 4);
+            assert!(dbc.messages[0].signals.is_empty());
+            assert_eq!(dbc.messages[1].signals.len(), 1);
+            assert!(dbc.messages[2].signals.is_empty());
+            assert_eq!(dbc.messages[3].name, "Reserved3");
+            assert!(dbc.messages[3].signals.is_empty());
+            assert_eq!(parse(&dbc.to_dbc_string()).unwrap(), dbc);
+        }
+    }
+
+    #[test]
+    fn value_description_lookup() {
+        let setup = Setup::new();
+        let contents = format!("{}
+VAL_ 2566117891 dummy1sg1 0 \"Off\" 1 \"On\" ;
+", setup.test_messages);
+        let dbc = parse(&contents).unwrap();
+        let signal = &dbc.messages[0].signals[0];
+        assert_eq!(signal.description_for_raw(0), Some("Off"));
+        assert_eq!(signal.description_for_raw(1), Some("On"));
+        assert_eq!(signal.description_for_raw(2), None);
+        assert_eq!(signal.raw_for_description("Off"), Some(0));
+        assert_eq!(signal.raw_for_description("On"), Some(1));
+        assert_eq!(signal.raw_for_description("on"), None);
+    }
+
+    #[test]
+    fn multi_line_comment_blocks() {
+        let setup = Setup::new();
+        let contents = format!("{}
+CM_ BU_ TCU \"Transmission control unit\r
+\r
+Sends the gear\";\r
+CM_ BO_ 2566117891 \"First line
+second line
+third line\" ;
+BA_DEF_ BO_ \"GenMsgCycleTime\" INT 0 65535;
+", setup.test_messages);
+        let dbc = parse(&contents).unwrap();
+        assert_eq!(dbc.nodes[0].comment, Some("Transmission control unit\n\nSends the gear".to_string()));
+        assert_eq!(dbc.messages[0].comment, Some("First line\nsecond line\nthird line".to_string()));
+        assert_eq!(dbc.attribute_definitions.len(), 1);
+        assert_eq!(parse(&dbc.to_dbc_string()).unwrap(), dbc);
+    }
+
+    #[test]
+    fn data_length_code() {
+        let mut message = Message::new(1, "Msg", 48);
+        assert_eq!(message.dlc(), 14);
+        assert_eq!(Message::from_dlc(14), Some(48));
+
+        message.size = 8;
+        assert_eq!(message.dlc(), 8);
+        message.size = 10;
+        assert_eq!(message.dlc(), 9);
+        message.size = 64;
+        assert_eq!(message.dlc(), 15);
+
+        assert_eq!(Message::from_dlc(0), Some(0));
+        assert_eq!(Message::from_dlc(9), Some(12));
+        assert_eq!(Message::from_dlc(15), Some(64));
+        assert_eq!(Message::from_dlc(16), None);
+    }
+
+    #[test]
+    fn invalid_identifiers() {
+        assert_eq!(parse_signal("SG_ 1bad : 0|8@1+ (1,0) [0|0] \"\" Vector__XXX").err(), Some(DbcError::InvalidContent));
+        assert_eq!(parse_signal("SG_ _good1 : 0|8@1+ (1,0) [0|0] \"\" Vector__XXX").unwrap().name, "_good1");
+        assert_eq!(parse_message("BO_ 1 2Msg: 8 Vector__XXX").err(), Some(DbcError::InvalidContent));
+        assert_eq!(parse_message("BO_ 1 Msg: 8 1TCU").err(), Some(DbcError::InvalidContent));
+        assert_eq!(parse_message("BO_ 1 Msg: 8 _TCU1").unwrap().transmitters, vec!["_TCU1"]);
+        assert_eq!(parse_nodes("BU_: TCU 3ECU").err(), Some(DbcError::InvalidContent));
+
+        match parse("BO_ 1 Msg: 8 Vector__XXX\n SG_ 1bad : 0|8@1+ (1,0) [0|0] \"\" Vector__XXX\n").err().unwrap() {
+            DbcError::InvalidLine { line, reason, .. } => {
+                assert_eq!(line, 2);
+                assert_eq!(reason, "Invalid signal, expected the signal name");
+            },
+            err => panic!("unexpected error {:?}", err),
+        }
+        match parse("BO_ 1 Msg: 8 1TCU\n").err().unwrap() {
+            DbcError::InvalidLine { reason, .. } => assert_eq!(reason, "Invalid message start, expected the transmitter"),
+            err => panic!("unexpected error {:?}", err),
+        }
+    }
+
+    #[test]
+    fn sanitize_name() {
+        assert_eq!(Signal::sanitize_name("1bad"), "_1bad");
+        assert_eq!(Signal::sanitize_name("wheel speed (km/h)"), "wheel_speed__km_h_");
+        assert_eq!(Signal::sanitize_name("Geschwindigkeit_\u{fc}"), "Geschwindigkeit__");
+        assert_eq!(Signal::sanitize_name("valid_name"), "valid_name");
+        assert_eq!(Signal::sanitize_name(""), "_");
+    }
+
+    #[test]
+    fn reindex_messages() {
+        let setup = Setup::new();
+        let mut dbc = parse(setup.test_messages).unwrap();
+        dbc.messages.insert(0, Message::new(1, "Added", 8));
+        dbc.reindex();
+        assert_eq!(dbc.message_by_id(1).unwrap().name, "Added");
+        assert_eq!(dbc.message_by_id(2565921559).unwrap().name, "MsgDummy2");
+        assert_eq!(dbc.message_index[&2565921559], 2);
+    }
+
+    #[test]
+    fn byte_order_sign_spacing() {
+        let signal = parse_signal("SG_ sig : 0|8@1 - (1,0) [0|0] \"\" Vector__XXX").unwrap();
+        assert_eq!(signal.byte_order(), ByteOrder::LittleEndian);
+        assert!(signal.is_signed);
+
+        let signal = parse_signal("SG_ sig : 7|8@0\t+ (1,0) [0|0] \"\" Vector__XXX").unwrap();
+        assert_eq!(signal.byte_order(), ByteOrder::BigEndian);
+        assert!(!signal.is_signed);
+
+        assert_eq!(parse_signal("SG_ sig : 0|8@1 x (1,0) [0|0] \"\" Vector__XXX").err(), Some(DbcError::InvalidContent));
+        assert_eq!(parse_signal("SG_ sig : 0|8@ 1+ (1,0) [0|0] \"\" Vector__XXX").err(), Some(DbcError::InvalidContent));
+    }
+
+    #[test]
+    fn exporters() {
+        let setup = Setup::new();
+        let dbc = parse(setup.test_messages).unwrap();
+
+        let exported = DbcExporter.export(&dbc);
+        assert_eq!(exported, dbc.to_dbc_string());
+        assert_eq!(parse(&exported).unwrap(), dbc);
+
+        let exported = CsvExporter.export(&dbc);
+        assert_eq!(exported.lines().count(), 1 + dbc.iter_signals().count());
+        assert!(exported.contains("2565921559,MsgDummy2,gps_longitude,39,32,Motorola,true"));
+
+        #[cfg(feature = "serde")]
+        {
+            let exported = JsonExporter.export(&dbc);
+            let value: serde_json::Value = serde_json::from_str(&exported).unwrap();
+            assert_eq!(value["messages"][1]["name"], "MsgDummy2");
+        }
+
+        let exporters: Vec<Box<dyn Exporter>> = vec![Box::new(DbcExporter), Box::new(CsvExporter)];
+        assert!(exporters.iter().all(|exporter| !exporter.export(&dbc).is_empty()));
+    }
+
+    #[test]
+    fn decode_into_buffer() {
+        let setup = Setup::new();
+        let contents = format!("{}
+BO_ 100 Mux: 8 Vector__XXX
+ SG_ mode M : 0|8@1+ (1,0) [0|0] \"\" Vector__XXX
+ SG_ speed m1 : 8|16@1+ (0.1,0) [0|0] \"\" Vector__XXX
+ SG_ temperature m2 : 8|8@1- (1,-40) [0|0] \"\" Vector__XXX
+", setup.test_messages);
+        let dbc = parse(&contents).unwrap();
+        let data = [0x01, 0xE8, 0x03, 0x03, 0x04, 0x05, 0x06, 0x07, 0xFF];
+
+        let mut buffer = Vec::new();
+        for message in &dbc {
+            buffer.clear();
+            message.decode_into(&data, &mut buffer);
+            let decoded = message.decode(&data);
+            assert_eq!(buffer.len(), decoded.len());
+            for &(i, value) in &buffer {
+                assert_eq!(decoded[&message.signals[i].name], value);
+            }
+        }
+
+        let mux = dbc.message_by_id(100).unwrap();
+        buffer.clear();
+        mux.decode_into(&data, &mut buffer);
+        assert_eq!(buffer, vec![(0, 1.0), (1, 100.0)]);
+    }
+
+    #[test]
+    fn value_tables() {
+        let setup = Setup::new();
+        let contents = format!("BU_: TCU
+VAL_TABLE_ OnOff 0 \"Off\" 1 \"On\" ;
+VAL_TABLE_ Empty ;
+{}
+VAL_ 2566117891 dummy1sg1 OnOff ;
+VAL_ 2566117891 dummy1sg3 Unknown ;
+", setup.test_messages);
+        let dbc = parse(&contents).unwrap();
+        assert_eq!(dbc.value_tables.len(), 2);
+        assert_eq!(dbc.value_tables["OnOff"][&0], "Off");
+        assert_eq!(dbc.value_tables["OnOff"][&1], "On");
+        assert!(dbc.value_tables["Empty"].is_empty());
+        assert_eq!(dbc.messages[0].signals[0].value_descriptions, dbc.value_tables["OnOff"]);
+        assert!(dbc.messages[0].signals[2].value_descriptions.is_empty());
+        assert!(dbc.warnings.is_empty());
+        assert_eq!(parse(&dbc.to_dbc_string()).unwrap(), dbc);
+
+        assert!(parse("VAL_TABLE_ OnOff 0 \"Off\" 1 ;").is_err());
+    }
+
+    #[test]
+    fn used_bits() {
+        let setup = Setup::new();
+        let dbc = parse(setup.test_messages).unwrap();
+        assert_eq!(dbc.messages[0].used_bits(), 36);
+        assert_eq!(dbc.messages[0].packing_density(), 0.5625);
+        assert_eq!(dbc.messages[1].used_bits(), 64);
+        assert_eq!(dbc.messages[1].packing_density(), 1.0);
+
+        let mut message = Message::new(1, "Msg", 0);
+        assert_eq!(message.used_bits(), 0);
+        assert_eq!(message.packing_density(), 0.0);
+        message.size = 1;
+        message.signals = vec![Signal::new("a", 0, 4), Signal::new("b", 2, 4)];
+        assert_eq!(message.used_bits(), 6);
+    }
+
+    #[test]
+    fn signals_without_indentation() {
+        let contents = "BU_: TCU
+BO_ 1 First: 8 TCU
+SG_ a : 0|8@1+ (1,0) [0|0] \"\" Vector__XXX
+SG_ b : 8|8@1+ (1,0) [0|0] \"\" Vector__XXX
+BO_ 2 Second: 8 TCU
+\tSG_ c : 0|8@1+ (1,0) [0|0] \"\" Vector__XXX
+SG_ d : 8|8@1+ (1,0) [0|0] \"\" Vector__XXX
+";
+        let dbc = parse(contents).unwrap();
+        let names: Vec<Vec<&str>> = dbc.messages.iter()
+            .map(|message| message.signals.iter().map(|signal| signal.name.as_str()).collect())
+            .collect();
+        assert_eq!(names, vec![vec!["a", "b"], vec!["c", "d"]]);
+
+        let mut visitor = CountingVisitor::default();
+        parse_with_visitor(contents, &mut visitor).unwrap();
+        assert_eq!(visitor.signals, vec![
+            (1, "a".to_string()), (1, "b".to_string()), (2, "c".to_string()), (2, "d".to_string())
+        ]);
+    }
+
+    #[test]
+    fn audit_names() {
+        let setup = Setup::new();
+        let dbc = parse(setup.test_messages).unwrap();
+        assert!(dbc.audit_names().is_empty());
+
+        let contents = format!("{}
+BO_ 1 MsgDummy1: 8 Vector__XXX
+ SG_ dummy1sg1 : 0|8@1+ (1,0) [0|0] \"\" Vector__XXX
+ SG_ unique : 8|8@1+ (1,0) [0|0] \"\" Vector__XXX
+
+BO_ 2565921559 Other: 8 Vector__XXX
+ SG_ other : 0|8@1+ (1,0) [0|0] \"\" Vector__XXX
+ SG_ other : 8|8@1+ (1,0) [0|0] \"\" Vector__XXX
+ SG_ dummy1sg1 : 16|8@1+ (1,0) [0|0] \"\" Vector__XXX
+", setup.test_messages);
+        let audit = parse(&contents).unwrap().audit_names();
+        assert_eq!(audit, NameAudit {
+            duplicate_message_names: vec!["MsgDummy1".to_string()],
+            duplicate_signal_names: vec!["dummy1sg1".to_string(), "other".to_string()],
+            duplicate_message_ids: vec![2565921559]
+        });
+        assert!(!audit.is_empty());
+    }
+
+    #[test]
+    fn node_set() {
+        let first = parse_nodes("BU_: TCU").unwrap();
+        let second = parse_nodes("BU_: TCU VEHICLE").unwrap();
+        let nodes: std::collections::HashSet<Node> = first.into_iter().chain(second).collect();
+        assert_eq!(nodes.len(), 2);
+        assert!(nodes.contains(&Node { name: "TCU".to_string(), comment: None }));
+
+        let nodes: std::collections::HashSet<Node> = parse_nodes("BU_: ECU ECU").unwrap().into_iter().collect();
+        assert_eq!(nodes.len(), 1);
+
+        let commented = Node { name: "ECU".to_string(), comment: Some("Engine".to_string()) };
+        let nodes: std::collections::HashSet<Node> = parse_nodes("BU_: ECU").unwrap().into_iter()
+            .chain(std::iter::once(commented.clone()))
+            .collect();
+        assert_eq!(nodes.len(), 2);
+        assert!(nodes.contains(&commented));
+    }
+
+    #[test]
+    fn node_names_keep_their_first_spelling() {
+        let dbc = parse("BU_: ECU\nBU_: ecu TCU\n\nCM_ BU_ ecu \"Engine\";\n").unwrap();
+        assert_eq!(dbc.nodes, vec![
+            Node { name: "ECU".to_string(), comment: Some("Engine".to_string()) },
+            Node { name: "TCU".to_string(), comment: None }
+        ]);
+    }
+
+    #[test]
+    fn physical_and_raw_range() {
+        let setup = Setup::new();
+        let dbc = parse(setup.test_messages).unwrap();
+        let gps_longitude = &dbc.messages[1].signals[0];
+        assert_eq!(gps_longitude.physical_min(), -214.7483648);
+        assert_eq!(gps_longitude.physical_max(), 214.7483647);
+        assert_eq!(gps_longitude.raw_min(), -2147483648);
+        assert_eq!(gps_longitude.raw_max(), 2147483647);
+
+        let signal = parse_signal("SG_ sig : 0|8@1+ (-0.5,10) [-117.5|10] \"\" Vector__XXX").unwrap();
+        assert_eq!(signal.raw_min(), 0);
+        assert_eq!(signal.raw_max(), 255);
+    }
+
+    #[test]
+    fn strict_parse() {
+        let setup = Setup::new();
+        assert_eq!(parse_strict(setup.test_messages).unwrap(), parse(setup.test_messages).unwrap());
+
+        // Environment variables are supported, so they pass
+        let contents = format!("{}
+EV_ Dummy: 0 [0|1] \"\" 0 1 DUMMY_NODE_VECTOR0 Vector__XXX;
+", setup.test_messages);
+        assert_eq!(parse_strict(&contents).unwrap().env_vars.len(), 1);
+
+        let contents = format!("{}
+BA_DEF_REL_ BU_SG_REL_ \"Dummy\" INT 0 1;
+FOO_ bar;
+", setup.test_messages);
+        assert!(parse(&contents).is_ok());
+        assert_eq!(parse_strict(&contents).err(), Some(DbcError::InvalidLine {
+            line: 17,
+            content: "BA_DEF_REL_ BU_SG_REL_ \"Dummy\" INT 0 1;".to_string(),
+            reason: "Unsupported line"
+        }));
+        assert_eq!(parse_strict("BU_: TCU\nFOO_ bar;\n").err(), Some(DbcError::InvalidLine {
+            line: 2, content: "FOO_ bar;".to_string(), reason: "Unsupported line"
+        }));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn cantools_export() {
+        let setup = Setup::new();
+        let dbc = parse(setup.test_messages).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&CantoolsExporter.export(&dbc)).unwrap();
+
+        let message = &value["messages"][1];
+        assert_eq!(message["name"], "MsgDummy2");
+        assert_eq!(message["frame_id"], 0x18F0DB17);
+        assert_eq!(message["is_extended_frame"], true);
+        assert_eq!(message["length"], 8);
+
+        let signal = &message["signals"][0];
+        assert_eq!(signal["name"], "gps_longitude");
+        assert_eq!(signal["start"], 39);
+        assert_eq!(signal["length"], 32);
+        assert_eq!(signal["byte_order"], "big_endian");
+        assert_eq!(signal["is_signed"], true);
+        assert_eq!(signal["scale"], 1e-7);
+        assert_eq!(signal["minimum"], -214.7483648);
+        assert_eq!(signal["unit"], "deg");
+        assert_eq!(value["messages"][0]["signals"][0]["byte_order"], "little_endian");
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn parse_gzip_file() {
+        use std::io::Write;
+
+        let setup = Setup::new();
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(setup.test_messages.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let path = std::env::temp_dir().join(format!("import_dbc_{}.dbc.gz", std::process::id()));
+        std::fs::write(&path, &compressed).unwrap();
+        let dbc = parse_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(dbc.unwrap(), parse(setup.test_messages).unwrap());
+
+        let path = std::env::temp_dir().join(format!("import_dbc_{}_truncated.dbc.gz", std::process::id()));
+        std::fs::write(&path, &compressed[..compressed.len() / 2]).unwrap();
+        let dbc = parse_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        let err = dbc.unwrap_err();
+        assert!(matches!(err.downcast_ref::<DbcError>(), Some(DbcError::IoError(_))));
+    }
+
+    #[test]
+    fn signal_default_value() {
+        let contents = "BO_ 100 Msg: 8 Vector__XXX
+ SG_ started : 0|8@1+ (0.5,-40) [-40|87.5] \"\" Vector__XXX
+ SG_ plain : 8|8@1+ (1,0) [0|0] \"\" Vector__XXX
+ SG_ offset_range : 16|8@1+ (1,10) [10|265] \"\" Vector__XXX
+ SG_ negative_range : 24|8@1- (1,0) [-100|-10] \"\" Vector__XXX
+
+BA_DEF_ SG_ \"GenSigStartValue\" INT 0 255;
+BA_ \"GenSigStartValue\" SG_ 100 started 100;
+";
+        let dbc = parse(contents).unwrap();
+        let message = &dbc.messages[0];
+        assert_eq!(message.signals[0].default_value(), 10.0);
+        assert_eq!(message.signals[1].default_value(), 0.0);
+        assert_eq!(message.signals[2].default_value(), 10.0);
+        assert_eq!(message.signals[3].default_value(), -10.0);
+    }
+
+    #[test]
+    fn messages_in_range() {
+        let setup = Setup::new();
+        let dbc = parse(setup.test_messages).unwrap();
+        let names = |messages: Vec<&Message>| messages.iter().map(|message| message.name.clone()).collect::<Vec<_>>();
+
+        // 0x18F0DB17 and 0x18F1DA03 are the ids of MsgDummy2 and MsgDummy3
+        assert_eq!(names(dbc.messages_in_range(0x18F0DB17, 0x18F1DA03)), vec!["MsgDummy2", "MsgDummy3"]);
+        assert_eq!(names(dbc.messages_in_range(0x18F0DB17, 0x18F0DB17)), vec!["MsgDummy2"]);
+        assert_eq!(names(dbc.messages_in_range(0, 0x1FFFFFFF)).len(), 3);
+        assert!(dbc.messages_in_range(0, 0x7FF).is_empty());
+        assert!(dbc.messages[1].in_id_range(0x18F0DB17, 0x18F0DB17));
+        assert!(!dbc.messages[1].in_id_range(0x18F0DB18, 0x18F1DA03));
+
+        let mut retained = dbc.clone();
+        retained.retain_id_range(0x18F0DB17, 0x18F1DA03);
+        assert_eq!(names(retained.messages.iter().collect()), vec!["MsgDummy2", "MsgDummy3"]);
+        assert_eq!(retained.message_by_id(2565986819).unwrap().name, "MsgDummy3");
+        assert!(retained.message_by_id(2566117891).is_none());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn config_id_range() {
+        let args: Vec<String> = ["prog", "--id-range", "0x100-0x1FF", "a.dbc"].iter().map(|arg| arg.to_string()).collect();
+        assert_eq!(Config::new(&args).unwrap().id_range, Some((0x100, 0x1FF)));
+
+        let args: Vec<String> = ["prog", "--id-range=256-511", "a.dbc"].iter().map(|arg| arg.to_string()).collect();
+        assert_eq!(Config::new(&args).unwrap().id_range, Some((256, 511)));
+
+        let args: Vec<String> = ["prog", "a.dbc"].iter().map(|arg| arg.to_string()).collect();
+        assert_eq!(Config::new(&args).unwrap().id_range, None);
+
+        for range in &["512-256", "0x100", "a-b", ""] {
+            let args: Vec<String> = vec!["prog".to_string(), format!("--id-range={}", range), "a.dbc".to_string()];
+            assert_eq!(Config::new(&args).err(), Some("invalid id range"));
+        }
+
+        let args: Vec<String> = ["prog", "a.dbc", "--id-range"].iter().map(|arg| arg.to_string()).collect();
+        assert_eq!(Config::new(&args).err(), Some("missing id range"));
+    }
+
+    #[test]
+    fn bit_timing() {
+        let setup = Setup::new();
+        let with_baudrate = format!("VERSION \"\"\n\nNS_ :\n\tCM_\n\nBS_: 500000\n{}", setup.test_messages);
+        let dbc = parse(&with_baudrate).unwrap();
+        assert_eq!(dbc.baudrate, Some(500000));
+        assert_eq!(dbc.messages.len(), 3);
+        assert!(dbc.warnings.is_empty());
+        assert_eq!(parse(&dbc.to_dbc_string()).unwrap(), dbc);
+
+        assert_eq!(parse(&format!("BS_:\n{}", setup.test_messages)).unwrap().baudrate, None);
+        assert_eq!(parse("BS_: 250000 : 12,34").unwrap().baudrate, Some(250000));
+        assert_eq!(parse(setup.test_messages).unwrap().baudrate, None);
+        assert!(parse("BS_: fast").is_err());
+        assert!(parse("BS_: 99999999999").is_err());
+    }
+
+    #[test]
+    fn contains_bit() {
+        let setup = Setup::new();
+        let dbc = parse(setup.test_messages).unwrap();
+        let message = &dbc.messages[0];
+        assert_eq!(message.contains_bit(34).unwrap().name, "dummy1sg1");
+        assert_eq!(message.contains_bit(0).unwrap().name, "dummy1sg4");
+        assert_eq!(message.contains_bit(18).unwrap().name, "dummy1sg2");
+        assert!(message.contains_bit(36).is_none());
+        assert!(message.contains_bit(63).is_none());
+
+        // Motorola signals are found by the bits they really cover
+        let gps = &dbc.messages[1];
+        assert_eq!(gps.contains_bit(39).unwrap().name, "gps_longitude");
+        assert_eq!(gps.contains_bit(32).unwrap().name, "gps_longitude");
+        assert_eq!(gps.contains_bit(0).unwrap().name, "gps_latitude");
+    }
+
+    #[test]
+    fn float_signals() {
+        let contents = "BO_ 100 Floats: 16 Vector__XXX
+ SG_ single : 0|32@1- (1,0) [0|0] \"\" Vector__XXX
+ SG_ double : 64|64@1- (1,0) [0|0] \"\" Vector__XXX
+ SG_ integer : 32|32@1- (1,0) [0|0] \"\" Vector__XXX
+
+SIG_VALTYPE_ 100 single : 1;
+SIG_VALTYPE_ 100 double : 2;
+";
+        let dbc = parse(contents).unwrap();
+        let message = &dbc.messages[0];
+        assert_eq!(message.signals[0].value_type, ValueType::Float);
+        assert_eq!(message.signals[1].value_type, ValueType::Double);
+        assert_eq!(message.signals[2].value_type, ValueType::Integer);
+        assert!(dbc.warnings.is_empty());
+
+        let mut data = [0u8; 16];
+        data[..4].copy_from_slice(&1.5f32.to_le_bytes());
+        data[4..8].copy_from_slice(&(-7i32).to_le_bytes());
+        data[8..].copy_from_slice(&(-0.1f64).to_le_bytes());
+        let decoded = message.decode(&data);
+        assert_eq!(decoded["single"], 1.5);
+        assert_eq!(decoded["double"], -0.1);
+        assert_eq!(decoded["integer"], -7.0);
+
+        assert_eq!(message.encode(&decoded), data.to_vec());
+        assert_eq!(parse(&dbc.to_dbc_string()).unwrap(), dbc);
+
+        assert!(parse("SIG_VALTYPE_ 100 single : 4;").is_err());
+    }
+
+    #[test]
+    fn encode_signals_wider_than_64_bits() {
+        let contents = "BO_ 1 M: 16 Vector__XXX
+ SG_ s : 0|72@1+ (1,0) [0|0] \"\" Vector__XXX
+ SG_ m : 7|72@0+ (1,0) [0|0] \"\" Vector__XXX
+";
+        let dbc = parse(contents).unwrap();
+        let message = &dbc.messages[0];
+        let mut data = [0xFFu8; 16];
+        message.signals[0].encode(258.0, &mut data);
+        assert_eq!(&data[..9], &[0x02, 0x01, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(message.signals[0].decode(&data), 258.0);
+
+        let mut data = [0xFFu8; 16];
+        message.signals[1].encode(258.0, &mut data);
+        assert_eq!(&data[..9], &[0, 0, 0, 0, 0, 0, 0, 0x01, 0x02]);
+        assert_eq!(message.signals[1].decode(&data), 258.0);
+    }
+
+    #[test]
+    fn decode_signals_past_the_last_bit() {
+        let mut motorola = Signal::new("motorola", 65528, 16);
+        motorola.is_little_endian = false;
+        let mut message = Message::new(1, "M", 8);
+        message.signals = vec![Signal::new("intel", 65535, 2), motorola];
+
+        let decoded = message.decode(&[0xFF; 8]);
+        assert_eq!(decoded["intel"], 0.0);
+        assert_eq!(decoded["motorola"], 0.0);
+    }
+
+    #[test]
+    fn encode_signals_past_the_last_bit() {
+        let mut motorola = Signal::new("motorola", 65528, 16);
+        motorola.is_little_endian = false;
+        let mut message = Message::new(1, "M", 8);
+        message.signals = vec![Signal::new("intel", 65535, 2), motorola];
+
+        let values: HashMap<String, f64> = [("intel".to_string(), 3.0), ("motorola".to_string(), 1.0)]
+            .iter().cloned().collect();
+        assert_eq!(message.encode(&values), vec![0; 8]);
+    }
+
+    #[test]
+    fn validate_signals_past_the_last_bit() {
+        let mut motorola = Signal::new("motorola", 65528, 16);
+        motorola.is_little_endian = false;
+        let mut message = Message::new(1, "M", 8);
+        message.signals = vec![Signal::new("intel", 65535, 65535), motorola, Signal::new("edge", 510, 4)];
+
+        assert!(message.signals[0].bit_positions().is_empty());
+        assert!(message.signals[1].bit_positions().is_empty());
+        assert_eq!(message.signals[2].bit_positions(), vec![510, 511]);
+        assert_eq!(message.signals[0].msb_start_bit(), u16::MAX);
+        assert_eq!(message.used_bits(), 0);
+        assert!(message.contains_bit(0).is_none());
+        assert!(message.overlapping_signals().is_empty());
+        assert!(message.bit_layout().iter().flatten().all(|bit| bit.is_none()));
+
+        let dbc = DbcBuilder::new().add_message(message).build();
+        let out_of_bounds: Vec<String> = dbc.validate().into_iter()
+            .filter_map(|error| match error {
+                ValidationError::SignalOutOfBounds { signal, .. } => Some(signal),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(out_of_bounds, vec!["intel", "motorola", "edge"]);
+    }
+
+    #[test]
+    fn float_helpers() {
+        for value in [0.0, 0.4, 0.5, 1.5, 2.5, -0.5, -1.49, -2.5, 1e300, -4503599627370497.0, 123456.789] {
+            assert_eq!(round(value), value.round(), "{}", value);
+        }
+        assert!(round(f64::NAN).is_nan());
+        assert_eq!(round(f64::INFINITY), f64::INFINITY);
+
+        for exponent in [-1, 0, 1, 7, 63, 64, 1023] {
+            assert_eq!(pow2(exponent), 2f64.powi(exponent), "{}", exponent);
+        }
+        assert_eq!(pow2(1024), f64::INFINITY);
+        assert_eq!(pow2(65534), f64::INFINITY);
     }
 }